@@ -0,0 +1,337 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, Format, LineNb, ParseError, SubtitleParser};
+use std::fmt::Display;
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+/// A parser of an ASS/SSA stream. Only the `[Events]` section is read.
+pub struct AssParser<R: Read> {
+    lines: LineNb<BufReader<R>>,
+    /// The columns given by the `Format:` line of the `[Events]` section.
+    columns: Option<Columns>,
+    /// True when the current section is `[Events]`.
+    events: bool,
+    end: bool,
+}
+impl<R: Read> AssParser<R> {
+    pub fn new(r: R) -> io::Result<Self> {
+        use std::io::BufRead;
+
+        let mut input = BufReader::new(r);
+
+        let first = input.fill_buf()?;
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
+            input.consume(3);
+        }
+
+        Ok(Self {
+            lines: LineNb::new(input),
+            columns: None,
+            events: false,
+            end: false,
+        })
+    }
+    /// Parse one line, return a cue if it's a dialogue line.
+    fn parse_line(&mut self, line: &str) -> io::Result<Option<Cue>> {
+        if line.starts_with('[') {
+            self.events = line.eq_ignore_ascii_case("[events]");
+            return Ok(None);
+        } else if !self.events {
+            return Ok(None);
+        }
+
+        if let Some(format) = line.strip_prefix("Format:") {
+            self.columns = Some(Columns::parse(format, self.lines.current())?);
+            Ok(None)
+        } else if let Some(dialogue) = line.strip_prefix("Dialogue:") {
+            let columns = match &self.columns {
+                Some(c) => c,
+                None => return ParseError::unexpected(line, self.lines.current()),
+            };
+
+            let fields: Vec<&str> = dialogue.splitn(columns.len, ',').collect();
+            if fields.len() != columns.len {
+                return ParseError::unexpected(line, self.lines.current());
+            }
+
+            Ok(Some(Cue::new(
+                None,
+                parse_duration(fields[columns.start].trim(), self.lines.current())?,
+                parse_duration(fields[columns.end].trim(), self.lines.current())?,
+                parse_text(fields[columns.text]),
+            )))
+        } else {
+            Ok(None)
+        }
+    }
+}
+impl<R: Read> SubtitleParser for AssParser<R> {
+    fn format(&self) -> Format {
+        Format::Ass
+    }
+}
+impl<R: Read> Iterator for AssParser<R> {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        if self.end {
+            return None;
+        }
+
+        loop {
+            let line = match self.lines.next() {
+                None => {
+                    self.end = true;
+                    return None;
+                }
+                Some(Err(e)) => {
+                    self.end = true;
+                    return Some(Err(e));
+                }
+                Some(Ok(l)) => l,
+            };
+
+            match self.parse_line(line.trim()) {
+                Ok(None) => {}
+                Ok(Some(c)) => return Some(Ok(c)),
+                Err(e) => {
+                    self.end = true;
+                    return Some(Err(self.lines.locate(e)));
+                }
+            }
+        }
+    }
+}
+#[test]
+fn assparser() {
+    let mut p = AssParser::new(
+        "[Script Info]
+Title: Test
+ScriptType: v4.00+
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize
+Style: Default,Arial,20
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+Comment: 0,0:00:00.00,0:00:01.00,Default,,0,0,0,,A comment
+Dialogue: 0,0:00:01.00,0:00:04.00,Default,,0,0,0,,Never drink {\\i1}liquid{\\i0} nitrogen.
+Dialogue: 0,0:00:05.50,0:00:09.25,Default,,0,0,0,,— It will perforate your stomach.\\N— You could die, really."
+            .as_bytes(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        )
+    );
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(5, 500_000_000),
+            Duration::new(9, 250_000_000),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die, really."),
+            ],
+        )
+    );
+    assert!(p.next().is_none());
+}
+
+/// The column indexes of the Dialogue lines.
+struct Columns {
+    start: usize,
+    end: usize,
+    text: usize,
+    /// The number of columns.
+    len: usize,
+}
+impl Columns {
+    /// Parse the content of the `Format:` line (without the prefix).
+    fn parse(s: &str, line: usize) -> io::Result<Self> {
+        let names: Vec<&str> = s.split(',').map(|n| n.trim()).collect();
+        let find = |name: &str| match names.iter().position(|n| n.eq_ignore_ascii_case(name)) {
+            Some(i) => Ok(i),
+            None => ParseError::unexpected(s, line),
+        };
+
+        Ok(Self {
+            start: find("Start")?,
+            end: find("End")?,
+            text: find("Text")?,
+            len: names.len(),
+        })
+    }
+}
+
+/// Remove the override tags (`{...}`) and split the text on each `\N`.
+fn parse_text(s: &str) -> Vec<String> {
+    let mut text = String::with_capacity(s.len());
+    let mut tag = false;
+    for c in s.chars() {
+        match c {
+            '{' => tag = true,
+            '}' if tag => tag = false,
+            _ if tag => {}
+            c => text.push(c),
+        }
+    }
+
+    text.split("\\N").map(String::from).collect()
+}
+#[test]
+fn test_parse_text() {
+    assert_eq!(
+        parse_text("{\\an8}Hello {\\b1}World{\\b0}\\NSecond line"),
+        vec![String::from("Hello World"), String::from("Second line")]
+    );
+}
+
+/// Parse a `h:mm:ss.cc` time code.
+fn parse_duration(s: &str, line: usize) -> io::Result<Duration> {
+    let split: Vec<&str> = s.split(':').take(4).collect();
+    if split.len() != 3 {
+        return ParseError::timestamp("Invalid duration syntax", s, line);
+    }
+
+    let second_part: Vec<&str> = split[2].split('.').take(3).collect();
+    if second_part.len() != 2 {
+        return ParseError::timestamp(
+            "Invalid duration syntax (second and centisecond part)",
+            s,
+            line,
+        );
+    }
+
+    fn parse<T: std::str::FromStr>(s: &str, line: usize) -> io::Result<T>
+    where
+        <T as std::str::FromStr>::Err: Display,
+    {
+        s.parse().or_else(|e| ParseError::timestamp(e, s, line))
+    }
+    let hour: u64 = parse(split[0], line)?;
+    let min: u64 = parse(split[1], line)?;
+    let sec: u64 = parse(second_part[0], line)?;
+    let cs: u32 = parse(second_part[1], line)?;
+    if cs > 99 {
+        return ParseError::timestamp("centisecond greater than 99", s, line);
+    }
+
+    Ok(Duration::new(hour * 3600 + min * 60 + sec, cs * 10_000_000))
+}
+#[test]
+fn test_parse_duration() {
+    assert_eq!(
+        Duration::new(3600 + 23 * 60 + 17, 480_000_000),
+        parse_duration("1:23:17.48", 0).unwrap()
+    );
+}
+
+/// Write all Cues from the input Iterator into the write W. Use ASS subtitle format.
+/// Return the number fo writed cue.
+pub fn out<I, W>(cues: I, mut w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    w.write_all(HEADER.as_bytes())?;
+
+    let mut nb = 0;
+    for c in cues {
+        w.write_all(b"Dialogue: 0,")?;
+        write_duration(&mut w, &c.begin)?;
+        w.write_all(b",")?;
+        write_duration(&mut w, &c.end)?;
+        writeln!(w, ",Default,,0,0,0,,{}", c.text.join("\\N"))?;
+        nb += 1;
+    }
+
+    Ok(nb)
+}
+#[test]
+fn test_out() {
+    fn dur(d: u64) -> Duration {
+        Duration::new(d, 0)
+    }
+
+    let cues = vec![
+        Cue::new(
+            None,
+            dur(1),
+            dur(4),
+            vec!["Never drink liquid nitrogen.".to_string()],
+        ),
+        Cue::new(
+            None,
+            dur(5),
+            Duration::new(3600 + 9, 999_000_000),
+            vec![
+                "— It will perforate your stomach.".to_string(),
+                "— You could die.".to_string(),
+            ],
+        ),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out(cues.into_iter(), &mut output).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        HEADER.to_string()
+            + "Dialogue: 0,0:00:01.00,0:00:04.00,Default,,0,0,0,,Never drink liquid nitrogen.
+Dialogue: 0,0:00:05.00,1:00:09.99,Default,,0,0,0,,— It will perforate your stomach.\\N— You could die.
+"
+    );
+
+    let cues: Vec<Cue> = AssParser::new(&output[..])
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect();
+    assert_eq!(cues[1].text.len(), 2);
+    assert_eq!(cues[1].end, Duration::new(3600 + 9, 990_000_000));
+}
+
+/// The header with the Script Info, a Default style and the Format line of the Events section.
+const HEADER: &str = "[Script Info]
+ScriptType: v4.00+
+WrapStyle: 0
+PlayResX: 384
+PlayResY: 288
+
+[V4+ Styles]
+Format: Name, Fontname, Fontsize, PrimaryColour, SecondaryColour, OutlineColour, BackColour, Bold, Italic, Underline, StrikeOut, ScaleX, ScaleY, Spacing, Angle, BorderStyle, Outline, Shadow, Alignment, MarginL, MarginR, MarginV, Encoding
+Style: Default,Arial,20,&H00FFFFFF,&H000000FF,&H00000000,&H00000000,0,0,0,0,100,100,0,0,1,2,2,2,10,10,10,1
+
+[Events]
+Format: Layer, Start, End, Style, Name, MarginL, MarginR, MarginV, Effect, Text
+";
+
+/// Write one time code with centiseconds. The milliseconds are truncated like
+/// in the WebVTT writer.
+fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), io::Error> {
+    let sec = d.as_secs();
+    write!(
+        w,
+        "{}:{:02}:{:02}.{:02}",
+        sec / 3600,
+        sec / 60 % 60,
+        sec % 60,
+        d.subsec_millis() / 10
+    )
+}
+#[test]
+fn test_write_duration() {
+    let d = Duration::new(2 * 3600 + 3 * 60 + 5, 849_999_999);
+    let mut out: Vec<u8> = Vec::new();
+    write_duration(&mut out, &d).unwrap();
+    assert_eq!(std::str::from_utf8(&out).unwrap(), "2:03:05.84");
+}
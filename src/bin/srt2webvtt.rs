@@ -1,7 +1,10 @@
 use srt2webvtt::*;
+use std::cell::Cell;
 use std::fs::File;
 use std::io::{self, Read, Write};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::time::Duration;
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
@@ -12,56 +15,1249 @@ struct Opt {
     /// The output subtitle format.
     #[structopt(long)]
     output_format: Option<Format>,
-    /// The delta time to apply one subtitle.
+    /// The framerate of the MicroDVD input or output, like `25` or `23.976`.
+    #[structopt(long)]
+    fps: Option<f64>,
+    /// Write each cue on one line, for the text output.
+    #[structopt(long)]
+    merge_lines: bool,
+    /// Write the JSON output without whitespace.
+    #[structopt(long)]
+    compact: bool,
+    /// Omit the blank line after the last cue of the SRT and WebVTT output.
+    #[structopt(long)]
+    no_trailing_newline: bool,
+    /// End the output lines with CRLF instead of LF.
+    #[structopt(long)]
+    crlf: bool,
+    /// Drop the cues without text, after the transforms.
+    #[structopt(long)]
+    drop_empty: bool,
+    /// Write the cue number as id of the WebVTT cues without id.
+    #[structopt(long)]
+    number_cues: bool,
+    /// Write all the ids of the input cues in the WebVTT output, the numeric
+    /// ones too.
+    #[structopt(long, conflicts_with = "drop-ids")]
+    keep_ids: bool,
+    /// Write no id in the WebVTT output.
+    #[structopt(long, conflicts_with = "number-cues")]
+    drop_ids: bool,
+    /// Write a dot instead of a comma before the milliseconds of the SRT
+    /// output.
+    #[structopt(long)]
+    srt_dot: bool,
+    /// The artist of the LRC output.
+    #[structopt(long)]
+    artist: Option<String>,
+    /// The title of the LRC output.
+    #[structopt(long)]
+    title: Option<String>,
+    /// The delta time to apply one subtitle: `+secs`, `-secs` or `*factor`.
+    /// Only one transform is applied per invocation.
     #[structopt(short, long, default_value = "0")]
     delta: Delta,
+    /// Two anchors `time=new_time` to retime the subtitle linearly, like
+    /// `--anchor 00:01:00=00:01:02 --anchor 01:00:00=01:00:30`.
+    #[structopt(long, number_of_values = 1)]
+    anchor: Vec<Anchor>,
+    #[structopt(flatten)]
+    transforms: Transforms,
+    /// The charset of the input, like `windows-1252`.
+    #[cfg(feature = "encoding")]
+    #[structopt(long)]
+    charset: Option<String>,
 
-    input: Option<PathBuf>,
+    /// The output file, else the standard output. Only with one input.
+    #[structopt(short, long)]
     output: Option<PathBuf>,
+    /// The directory where write each converted input, with the output format
+    /// extension.
+    #[structopt(long)]
+    out_dir: Option<PathBuf>,
+    /// Expand the inputs as glob patterns, like `subs/**/*.srt`.
+    #[structopt(long)]
+    glob: bool,
+    /// Rewrite each input file, by default in its own format.
+    #[structopt(long)]
+    in_place: bool,
+    /// Print the overlapping, empty or unordered cues, without writing any
+    /// output. Exit with an error if any is found.
+    #[structopt(long)]
+    check: bool,
+    /// Only parse each input, without writing any output. Exit with an error
+    /// on the first invalid input.
+    #[structopt(long)]
+    validate_only: bool,
+    /// Print the number of cues, the total duration, the first and the last
+    /// times and the average characters per second, without writing any
+    /// output.
+    #[structopt(long)]
+    stats: bool,
+    /// Join the inputs `file@offset` into one output, each input is shifted by
+    /// its offset, like `--concat a.srt@0 b.srt@01:05:03`.
+    #[structopt(long, min_values = 1, parse(try_from_str = parse_concat))]
+    concat: Vec<(PathBuf, Duration)>,
+    /// Merge the cues of this other track, like another language, into the
+    /// output. The cues which begin at the same time are stacked into one
+    /// cue. The other track is not shifted by the delta, the transforms are
+    /// applied after the merge.
+    #[structopt(long)]
+    merge_with: Option<PathBuf>,
+    /// The maximum distance between the begins of two stacked cues with
+    /// `--merge-with`.
+    #[structopt(long, default_value = "200ms", parse(try_from_str = parse_step))]
+    merge_tolerance: Duration,
+    /// The line between the stacked cues of `--merge-with`.
+    #[structopt(long, default_value = "---")]
+    merge_separator: String,
+    /// Split the input into segments of this duration, like `10s`, written
+    /// in `--split-dir` as `segment000.vtt`, `segment001.vtt`...
+    #[structopt(long, parse(try_from_str = parse_step))]
+    split: Option<Duration>,
+    /// The directory of the `--split` segments.
+    #[structopt(long, default_value = ".")]
+    split_dir: PathBuf,
+    /// Keep the absolute times in the `--split` segments, else the times are
+    /// rebased to the segment begin.
+    #[structopt(long)]
+    split_absolute: bool,
+
+    /// Do not print the number of printed cues.
+    #[structopt(short, long, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// The input files, else the standard input. With many inputs or with
+    /// `--out-dir`, each input is converted in its own file. Two files without
+    /// `--output`, `--out-dir`, `--glob` or `--in-place` are the input and the
+    /// output, unless the second one is an existing subtitle file.
+    inputs: Vec<PathBuf>,
+}
+
+impl Opt {
+    /// Use the second positional argument as the output, for the
+    /// `srt2webvtt input output` form. An existing second file with a
+    /// subtitle extension is an input, so it's never overwritten.
+    fn positional_output(&mut self) {
+        if self.inputs.len() == 2
+            && self.output.is_none()
+            && self.out_dir.is_none()
+            && !self.glob
+            && !self.in_place
+            && !(self.inputs[1].exists()
+                && guess_format(None, Some(&self.inputs[1]), None).is_some())
+        {
+            self.output = self.inputs.pop();
+        }
+    }
+    /// Print the summary of the conversion, unless `--quiet`. It's printed on
+    /// stderr when the subtitle is writed on stdout.
+    fn summary(&self, msg: &str) {
+        match (self.quiet, &self.output) {
+            (true, _) => {}
+            (false, None) => eprintln!("{}", msg),
+            (false, Some(_)) => println!("{}", msg),
+        }
+    }
+    /// The options of the writers.
+    fn write_options(&self) -> WriteOptions {
+        WriteOptions {
+            merge_lines: self.merge_lines,
+            compact: self.compact,
+            artist: self.artist.clone(),
+            title: self.title.clone(),
+            no_trailing_newline: self.no_trailing_newline,
+            number_cues: self.number_cues,
+            ids: match (self.keep_ids, self.drop_ids) {
+                (true, _) => IdPolicy::Keep,
+                (_, true) => IdPolicy::Drop,
+                _ => IdPolicy::Auto,
+            },
+            srt_dot: self.srt_dot,
+            line_ending: match self.crlf {
+                true => LineEnding::CrLf,
+                false => LineEnding::Lf,
+            },
+            drop_empty: self.drop_empty,
+            ..WriteOptions::default()
+        }
+    }
+}
+
+// The transforms applied on the cues after the delta.
+#[derive(StructOpt, Debug)]
+struct Transforms {
+    /// Remove the HTML and ASS formatting tags from the cue text.
+    #[structopt(long)]
+    strip_tags: bool,
+    /// Keep only the cues after this time, like `10:00`. The cues are
+    /// clamped into the window.
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    from: Option<Duration>,
+    /// Keep only the cues before this time, like `20:00`.
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    to: Option<Duration>,
+    /// Subtract the `--from` time, so the window starts at zero.
+    #[structopt(long)]
+    rebase: bool,
+    /// Apply a delta only on the cues after a time, like
+    /// `--offset-after 00:45:00=+12`.
+    #[structopt(long, parse(try_from_str = parse_offset_after))]
+    offset_after: Option<(Duration, Delta)>,
+    /// Shift all the cues so the last one ends at this time, like
+    /// `--end-at 01:30:00`. All the cues are keeped in memory.
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    end_at: Option<Duration>,
+    /// Shift all the cues so the first one begins at this time, like
+    /// `--shift-first-to 00:00:00`.
+    #[structopt(long, parse(try_from_str = parse_timestamp))]
+    shift_first_to: Option<Duration>,
+    /// Split the cues with more lines into sequential cues.
+    #[structopt(long)]
+    max_lines: Option<usize>,
+    /// The gap between the cues split by `--max-lines`, like `100ms`.
+    #[structopt(long, default_value = "0s", parse(try_from_str = parse_step))]
+    split_gap: Duration,
+    /// Round the times to a multiple of this step, like `100ms` or `1s`.
+    #[structopt(long, parse(try_from_str = parse_step))]
+    round: Option<Duration>,
+    /// Round down the times with `--round`.
+    #[structopt(long)]
+    truncate: bool,
+    /// Round the times to the nearest frame at this framerate, like `25` or
+    /// `29.97`.
+    #[structopt(long, parse(try_from_str = parse_fps))]
+    frame_round: Option<f64>,
+    /// Sort the cues by time. All the cues are keeped in memory.
+    #[structopt(long)]
+    sort: bool,
+    /// Merge the consecutive cues with the same text, if they are contiguous.
+    #[structopt(long)]
+    dedupe: bool,
+    /// Keep at least this blank time between the cues, like `40ms`. The end
+    /// of a cue is pulled back, a cue too short is keeped with a warning.
+    #[structopt(long, parse(try_from_str = parse_step))]
+    gap: Option<Duration>,
+    /// Replace the cues by a `[gap]` cue for each blank time longer than this
+    /// duration between them, like `5s`.
+    #[structopt(long, parse(try_from_str = parse_step))]
+    gaps: Option<Duration>,
+    /// Print the number of cues with a fraction of millisecond, truncated by
+    /// the SRT and WebVTT output.
+    #[structopt(long)]
+    warn_precision_loss: bool,
+    /// Keep only the first cues, after all the other transforms.
+    #[structopt(long)]
+    head: Option<usize>,
+    /// Keep only the last cues, after all the other transforms.
+    #[structopt(long)]
+    tail: Option<usize>,
+    /// Shorten the cues longer than this duration, like `7s`.
+    #[structopt(long, parse(try_from_str = parse_step))]
+    max_duration: Option<Duration>,
+    /// How `--max-duration` shortens a cue: `truncate` its end or `split` it
+    /// into cues with the same text.
+    #[structopt(long, default_value = "truncate", parse(try_from_str = parse_max_duration_mode))]
+    max_duration_mode: MaxDurationMode,
+    /// What to do with the cues which end at their begin: `keep`, `drop` or
+    /// `extend=500ms`. The extended cues never overlap the next cue.
+    #[structopt(long, parse(try_from_str = parse_zero_duration))]
+    zero_duration: Option<ZeroDurationPolicy>,
+    /// Pull back the end of each cue to the begin of the next cue, if it's
+    /// after.
+    #[structopt(long)]
+    no_overlap: bool,
+    /// Print the cues read faster than `--max-cps` characters per second.
+    #[structopt(long)]
+    cps_report: bool,
+    /// The maximum reading speed for `--cps-report`.
+    #[structopt(long, default_value = "20")]
+    max_cps: f64,
+    /// Join the lines of each cue into one line.
+    #[structopt(long)]
+    join_lines: bool,
+    /// The separator of the lines joined by `--join-lines`.
+    #[structopt(long, default_value = " ")]
+    join_sep: String,
+    /// Wrap the lines longer than this number of characters.
+    #[structopt(long)]
+    wrap: Option<usize>,
+    /// Replace a text by another in the cues, like `--replace Wrold=World`.
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_replace))]
+    replace: Vec<(String, String)>,
+    /// Replace a regex by a text in the cues, the text can use the capture
+    /// groups like `--replace-regex 'Mr (\w+)=$1'`.
+    #[cfg(feature = "regex")]
+    #[structopt(long, number_of_values = 1, parse(try_from_str = parse_replace_regex))]
+    replace_regex: Vec<(regex::Regex, String)>,
+    /// Normalize the typography: an em dash for the dialogue dashes, curly
+    /// quotes and no run of spaces.
+    #[structopt(long)]
+    normalize: bool,
+    /// Keep the straight quotes with `--normalize`.
+    #[structopt(long)]
+    keep_quotes: bool,
+    /// Change the case of the text: `upper`, `lower` or `sentence`.
+    #[structopt(long, parse(try_from_str = parse_case))]
+    case: Option<Case>,
+    /// Remove the empty lines of the cues, like after a `--replace`.
+    #[structopt(long)]
+    strip_empty_lines: bool,
+    /// Keep only the cues with a line which contains this text.
+    #[structopt(long)]
+    grep: Option<String>,
+    /// Keep only the cues with a line matched by this regex.
+    #[cfg(feature = "regex")]
+    #[structopt(long)]
+    grep_regex: Option<String>,
+    /// The `--grep-regex` built with the case flag by `build_grep_regex`.
+    #[cfg(feature = "regex")]
+    #[structopt(skip)]
+    grep_re: Option<regex::Regex>,
+    /// Match `--grep` and `--grep-regex` with the case, else the case is
+    /// ignored.
+    #[structopt(long)]
+    case_sensitive: bool,
+    /// Print on stderr the number of cues before and after the transforms
+    /// which drop, merge or split cues.
+    #[structopt(short, long)]
+    verbose: bool,
+}
+impl Transforms {
+    /// Build the regex of `--grep-regex` with the case flag, once before the
+    /// conversions.
+    #[cfg(feature = "regex")]
+    fn build_grep_regex(&mut self) -> Result<(), String> {
+        if let Some(pattern) = &self.grep_regex {
+            let re = regex::RegexBuilder::new(pattern)
+                .case_insensitive(!self.case_sensitive)
+                .build()
+                .map_err(|err| format!("--grep-regex: {}", err))?;
+            self.grep_re = Some(re);
+        }
+        Ok(())
+    }
+    fn apply<'a>(&self, mut cues: Cues<'a>) -> Cues<'a> {
+        if self.strip_tags {
+            cues = Box::new(strip_tags(cues));
+        }
+        for (from, to) in self.replace.iter() {
+            cues = Box::new(replace_text(cues, from, to));
+        }
+        #[cfg(feature = "regex")]
+        for (re, to) in self.replace_regex.iter() {
+            cues = Box::new(replace_regex(cues, re, to));
+        }
+        if self.normalize {
+            let typography = Typography {
+                quotes: !self.keep_quotes,
+                ..Typography::default()
+            };
+            cues = Box::new(normalize_typography(cues, typography));
+        }
+        if let Some(case) = self.case {
+            cues = Box::new(transform_case(cues, case));
+        }
+        if self.strip_empty_lines {
+            cues = Box::new(strip_empty_lines(cues));
+        }
+        if let Some(pattern) = &self.grep {
+            cues = self.logged("--grep", cues, |cues| match self.case_sensitive {
+                true => {
+                    let pattern = pattern.clone();
+                    Box::new(filter_text(cues, move |l| l.contains(pattern.as_str())))
+                }
+                false => {
+                    let pattern = pattern.to_lowercase();
+                    Box::new(filter_text(cues, move |l| {
+                        l.to_lowercase().contains(&pattern)
+                    }))
+                }
+            });
+        }
+        #[cfg(feature = "regex")]
+        if let Some(re) = &self.grep_re {
+            // The clone shares the compiled regex.
+            let re = re.clone();
+            cues = self.logged("--grep-regex", cues, |cues| {
+                Box::new(filter_text(cues, move |l| re.is_match(l)))
+            });
+        }
+        if self.join_lines {
+            cues = Box::new(join_lines(cues, &self.join_sep));
+        }
+        if let Some(max) = self.wrap {
+            cues = Box::new(wrap_lines(cues, max));
+        }
+        if let Some(n) = self.max_lines {
+            cues = self.logged("--max-lines", cues, |cues| {
+                Box::new(max_lines(cues, n, self.split_gap))
+            });
+        }
+        match (self.round, self.truncate) {
+            (Some(step), false) => cues = Box::new(round_times(cues, step)),
+            (Some(step), true) => cues = Box::new(truncate_times(cues, step)),
+            (None, _) => {}
+        }
+        if let Some(fps) = self.frame_round {
+            cues = Box::new(round_to_frames(cues, fps));
+        }
+        if self.sort {
+            cues = Box::new(sort_cues(cues));
+        }
+        if self.dedupe {
+            cues = self.logged("--dedupe", cues, |cues| Box::new(dedupe(cues)));
+        }
+        if let Some(cap) = self.max_duration {
+            let mode = self.max_duration_mode;
+            cues = self.logged("--max-duration", cues, |cues| {
+                Box::new(max_duration(cues, cap, mode))
+            });
+        }
+        if let Some(policy) = self.zero_duration {
+            cues = self.logged("--zero-duration", cues, |cues| {
+                Box::new(zero_duration(cues, policy))
+            });
+        }
+        if self.no_overlap {
+            cues = Box::new(trim_to_next(cues));
+        }
+        if let Some(gap) = self.gap {
+            cues = Box::new(enforce_gap_with(cues, gap, |w| eprintln!("{}", w)));
+        }
+        if self.cps_report {
+            let max = self.max_cps;
+            cues = Box::new(cues.enumerate().map(move |(i, c)| {
+                match cps(&c) {
+                    s if s.is_infinite() => eprintln!("cue {}: zero duration", i + 1),
+                    s if s > max => eprintln!("cue {}: {:.1} characters per second", i + 1, s),
+                    _ => {}
+                }
+                c
+            }));
+        }
+        if let Some((pivot, delta)) = &self.offset_after {
+            cues = Box::new(delta_after(cues, *pivot, delta.clone()));
+        }
+        if self.from.is_some() || self.to.is_some() {
+            cues = self.logged("--from/--to", cues, |cues| {
+                Box::new(window(cues, self.from.unwrap_or_default(), self.to))
+            });
+        }
+        if let (true, Some(from)) = (self.rebase, self.from) {
+            cues = Box::new(cues.map(Delta::Sub(from).applicator()));
+        }
+        if let Some(target) = self.end_at {
+            cues = Box::new(delta_to_end(cues, target).1.into_iter());
+        }
+        if let Some(target) = self.shift_first_to {
+            cues = Box::new(shift_first_to(cues, target));
+        }
+        if let Some(min) = self.gaps {
+            cues = self.logged("--gaps", cues, |cues| Box::new(gaps(cues, min)));
+        }
+        if let Some(n) = self.head {
+            cues = self.logged("--head", cues, |cues| Box::new(drop_empty(cues).take(n)));
+        }
+        if let Some(n) = self.tail {
+            cues = self.logged("--tail", cues, |cues| Box::new(tail(drop_empty(cues), n)));
+        }
+        if self.warn_precision_loss {
+            let mut lost = 0;
+            let mut done = false;
+            cues = Box::new(std::iter::from_fn(move || match cues.next() {
+                Some(c) => {
+                    lost += has_sub_millis(&c) as usize;
+                    Some(c)
+                }
+                None => {
+                    if !done {
+                        done = true;
+                        eprintln!("{} cues lose their sub-millisecond precision", lost);
+                    }
+                    None
+                }
+            }));
+        }
+        cues
+    }
+    /// With `--verbose`, print the number of cues before and after the
+    /// transform, when all the cues are readed.
+    fn logged<'a, T>(&self, name: &'static str, cues: Cues<'a>, transform: T) -> Cues<'a>
+    where
+        T: FnOnce(Cues<'a>) -> Cues<'a>,
+    {
+        if !self.verbose {
+            return transform(cues);
+        }
+        let before = Rc::new(Cell::new(0));
+        let counter = before.clone();
+        let cues = transform(Box::new(
+            cues.inspect(move |_| counter.set(counter.get() + 1)),
+        ));
+        let after = Rc::new(Cell::new(0));
+        let counter = after.clone();
+        let mut done = false;
+        Box::new(
+            cues.inspect(move |_| counter.set(counter.get() + 1))
+                .chain(std::iter::from_fn(move || {
+                    if !done {
+                        done = true;
+                        eprintln!("{}: {} cues -> {} cues", name, before.get(), after.get());
+                    }
+                    None
+                })),
+        )
+    }
+}
+#[cfg(feature = "regex")]
+#[test]
+fn grep_regex() {
+    let mut opt = Opt::from_iter(&["srt2webvtt", "--grep-regex", "k{50000}"]);
+    assert!(opt.transforms.build_grep_regex().is_err());
+    let mut opt = Opt::from_iter(&["srt2webvtt", "--grep-regex", "("]);
+    assert!(opt.transforms.build_grep_regex().is_err());
+
+    let cue = |text: &str| Cue::new(None, Duration::ZERO, Duration::ZERO, vec![text.into()]);
+    let mut opt = Opt::from_iter(&["srt2webvtt", "--grep-regex", "^hel+o$"]);
+    opt.transforms.build_grep_regex().unwrap();
+    let cues = vec![cue("Hello"), cue("World")];
+    let kept: Vec<Cue> = opt.transforms.apply(Box::new(cues.into_iter())).collect();
+    assert_eq!(kept, vec![cue("Hello")]);
 }
 
 fn main() -> Result<(), ()> {
-    let opt = Opt::from_args();
-    let input_format = get_format(opt.input_format, &opt.input, "input")?;
-    let output_format = get_format(opt.output_format, &opt.output, "output")?;
+    let mut opt = Opt::from_args();
+    let delta = get_delta(opt.delta.clone(), &opt.anchor)?;
+    opt.positional_output();
+    #[cfg(feature = "regex")]
+    if let Err(err) = opt.transforms.build_grep_regex() {
+        eprintln!("{}", err);
+        return Err(());
+    }
+    if opt.glob {
+        opt.inputs = expand_globs(&opt.inputs)?;
+    }
+    if opt.validate_only {
+        return validate_only(&opt);
+    }
+    if opt.check {
+        return check(&opt, delta);
+    }
+    if opt.stats {
+        return print_stats(&opt, delta);
+    }
+    if !opt.concat.is_empty() {
+        return concat_files(&opt, delta);
+    }
+    if let Some(window) = opt.split {
+        return match split_file(&opt, window, delta) {
+            Ok(nb) => {
+                if !opt.quiet {
+                    println!("{} segments printed in {}", nb, opt.split_dir.display());
+                }
+                Ok(())
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                Err(())
+            }
+        };
+    }
 
-    let input: Box<dyn Read> = match opt.input {
-        Some(p) => match File::open(p) {
-            Ok(f) => Box::new(f),
+    if !opt.glob && !opt.in_place && opt.inputs.len() <= 1 && opt.out_dir.is_none() {
+        let output_format =
+            get_format(opt.output_format, opt.output.as_deref(), opt.fps, "output")?;
+        return match convert_file(
+            &opt,
+            opt.inputs.first().map(PathBuf::as_path),
+            opt.output.as_deref(),
+            Some(output_format),
+            delta,
+        ) {
+            Ok(nb) => {
+                opt.summary(&format!("{} cues printed", nb));
+                Ok(())
+            }
             Err(err) => {
                 eprintln!("{}", err);
-                return Err(());
+                Err(())
             }
-        },
-        None => Box::new(io::stdin()),
+        };
+    }
+
+    if opt.output.is_some() {
+        eprintln!("Only one input with --output, use --out-dir for many inputs");
+        return Err(());
+    }
+    if opt.in_place && opt.out_dir.is_some() {
+        eprintln!("Can not use --in-place with --out-dir");
+        return Err(());
+    }
+    if opt.in_place && opt.inputs.is_empty() {
+        eprintln!("Need an input file with --in-place");
+        return Err(());
+    }
+    let output_format = match opt.in_place {
+        true => None,
+        false => Some(get_format(opt.output_format, None, opt.fps, "output")?),
     };
+    let convert_one = |input: &PathBuf| match output_format {
+        None => {
+            convert_in_place(&opt, input, delta.clone()).map(|nb| format!("{} cues printed", nb))
+        }
+        Some(f) => convert_to_dir(&opt, input, f, delta.clone()),
+    };
+    // The files are converted concurrently with the `parallel` feature.
+    #[cfg(feature = "parallel")]
+    let results: Vec<_> = {
+        use rayon::prelude::*;
+        opt.inputs.par_iter().map(convert_one).collect()
+    };
+    #[cfg(not(feature = "parallel"))]
+    let results: Vec<_> = opt.inputs.iter().map(convert_one).collect();
+    let mut results: Vec<_> = opt.inputs.iter().zip(results).collect();
+    results.sort_by(|a, b| a.0.cmp(b.0));
 
-    let output: Box<dyn Write> = match opt.output {
-        Some(p) => match File::create(p) {
-            Ok(f) => Box::new(f),
+    let mut failed = false;
+    for (input, result) in results {
+        match result {
+            Ok(_) if opt.quiet => {}
+            Ok(msg) => println!("{}: {}", input.display(), msg),
             Err(err) => {
-                eprintln!("{}", err);
+                eprintln!("{}: {}", input.display(), err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Convert the input in the output directory (or next to the input). Return
+/// the message to print.
+fn convert_to_dir(opt: &Opt, input: &Path, format: Format, delta: Delta) -> Result<String, String> {
+    let output = output_path(input, opt.out_dir.as_deref(), format);
+    if output == input {
+        return Err(String::from("the output is the input file"));
+    }
+    let nb = convert_file(opt, Some(input), Some(&output), Some(format), delta)?;
+    Ok(format!("{} cues printed in {}", nb, output.display()))
+}
+
+/// Convert the input into a temporary file in the same directory, and rename
+/// it over the input only on success. The output format is by default the
+/// input format.
+fn convert_in_place(opt: &Opt, input: &Path, delta: Delta) -> Result<usize, String> {
+    let name = input.file_name().unwrap_or_default().to_string_lossy();
+    let tmp = input.with_file_name(format!(".{}.{}.tmp", name, std::process::id()));
+    #[cfg(feature = "gzip")]
+    let tmp = match is_gz(input) {
+        true => tmp.with_extension("tmp.gz"),
+        false => tmp,
+    };
+    let result = convert_file(
+        opt,
+        Some(input),
+        Some(&tmp),
+        opt.output_format.map(|f| f.with_fps(opt.fps)),
+        delta,
+    )
+    .and_then(|nb| {
+        std::fs::rename(&tmp, input)
+            .map(|_| nb)
+            .map_err(|err| err.to_string())
+    });
+    if result.is_err() {
+        let _ = std::fs::remove_file(&tmp);
+    }
+    result
+}
+
+/// Expand each pattern into the matching paths. A pattern without match
+/// print a warning.
+fn expand_globs(patterns: &[PathBuf]) -> Result<Vec<PathBuf>, ()> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        let pattern = pattern.to_string_lossy();
+        let matches = match glob::glob(&pattern) {
+            Ok(m) => m,
+            Err(err) => {
+                eprintln!("Invalid pattern {:?}: {}", pattern, err);
                 return Err(());
             }
+        };
+        let len = paths.len();
+        for m in matches {
+            match m {
+                Ok(p) => paths.push(p),
+                Err(err) => eprintln!("{}", err),
+            }
+        }
+        if paths.len() == len {
+            eprintln!("Warning: no file match the pattern {:?}", pattern);
+        }
+    }
+    Ok(paths)
+}
+
+/// Convert the input file (or stdin) into the output file (or stdout), by
+/// default in the input format. Return the number of printed cues.
+fn convert_file(
+    opt: &Opt,
+    input: Option<&Path>,
+    output: Option<&Path>,
+    output_format: Option<Format>,
+    delta: Delta,
+) -> Result<usize, String> {
+    let (input_format, input) = open_input(opt, input)?;
+    let other = match &opt.merge_with {
+        Some(path) => Some(read_track(opt, path)?),
+        None => None,
+    };
+
+    let mut output = open_output(output)?;
+    let output_format = output_format.unwrap_or(input_format);
+    convert_with_options(
+        input,
+        input_format,
+        &mut output,
+        output_format,
+        delta,
+        |cues| match other {
+            Some(other) => {
+                let cues = merge_tracks(
+                    cues,
+                    other.into_iter(),
+                    opt.merge_tolerance,
+                    &opt.merge_separator,
+                );
+                opt.transforms.apply(Box::new(cues))
+            }
+            None => opt.transforms.apply(cues),
         },
-        None => Box::new(io::stdout()),
+        &opt.write_options(),
+    )
+    .and_then(|nb| output.finish().map(|_| nb))
+    .map_err(|err| err.to_string())
+}
+
+/// Parse all the cues of a file, like the `--merge-with` track.
+fn read_track(opt: &Opt, path: &Path) -> Result<Vec<Cue>, String> {
+    let error = |err: String| format!("{}: {}", path.display(), err);
+    let (format, r) = open_input(opt, Some(path)).map_err(error)?;
+    parse_reader(r, format)
+        .collect::<io::Result<Vec<Cue>>>()
+        .map_err(|err| error(err.to_string()))
+}
+
+/// Open the input file (or stdin), and get its format from the option, the
+/// extension or else the content.
+fn open_input(opt: &Opt, input: Option<&Path>) -> Result<(Format, Box<dyn Read>), String> {
+    let input_format = guess_format(opt.input_format, input, opt.fps);
+
+    let input: Box<dyn Read> = match input {
+        #[cfg(feature = "gzip")]
+        Some(p) if is_gz(p) => Box::new(flate2::read::GzDecoder::new(
+            File::open(p).map_err(|err| err.to_string())?,
+        )),
+        Some(p) => Box::new(File::open(p).map_err(|err| err.to_string())?),
+        None => Box::new(io::stdin()),
     };
+    #[cfg(feature = "encoding")]
+    let input: Box<dyn Read> = match &opt.charset {
+        Some(charset) => Box::new(decode_reader(input, charset)),
+        None => input,
+    };
+    match input_format {
+        Some(f) => Ok((f, input)),
+        None => match detect_format(input) {
+            Ok((f, r)) => Ok((f, Box::new(r))),
+            Err(err) => Err(format!("Need an format for the input: {}", err)),
+        },
+    }
+}
+
+/// Create the output file, or else use stdout. A `.gz` file is compressed
+/// with the `gzip` feature.
+fn open_output(output: Option<&Path>) -> Result<Output, String> {
+    match output {
+        #[cfg(feature = "gzip")]
+        Some(p) if is_gz(p) => Ok(Output::Gz(flate2::write::GzEncoder::new(
+            File::create(p).map_err(|err| err.to_string())?,
+            flate2::Compression::default(),
+        ))),
+        Some(p) => Ok(Output::File(
+            File::create(p).map_err(|err| err.to_string())?,
+        )),
+        None => Ok(Output::Stdout(io::stdout())),
+    }
+}
+
+/// The output of `open_output`. It must be finished to get the error of the
+/// last write, like the gzip trailer.
+enum Output {
+    Stdout(io::Stdout),
+    File(File),
+    #[cfg(feature = "gzip")]
+    Gz(flate2::write::GzEncoder<File>),
+}
+impl Output {
+    /// Flush the output and write the gzip trailer.
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Output::Stdout(mut w) => w.flush(),
+            Output::File(mut w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            Output::Gz(w) => w.finish()?.flush(),
+        }
+    }
+}
+impl Write for Output {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Output::Stdout(w) => w.write(buf),
+            Output::File(w) => w.write(buf),
+            #[cfg(feature = "gzip")]
+            Output::Gz(w) => w.write(buf),
+        }
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Output::Stdout(w) => w.flush(),
+            Output::File(w) => w.flush(),
+            #[cfg(feature = "gzip")]
+            Output::Gz(w) => w.flush(),
+        }
+    }
+}
+
+/// Return true if the file is gzip compressed, from its `.gz` extension.
+#[cfg(feature = "gzip")]
+fn is_gz(p: &Path) -> bool {
+    p.extension().is_some_and(|ext| ext == "gz")
+}
+
+/// The path without the `.gz` extension, to get the subtitle format.
+fn strip_gz(p: &Path) -> PathBuf {
+    #[cfg(feature = "gzip")]
+    if is_gz(p) {
+        return p.with_extension("");
+    }
+    p.to_path_buf()
+}
 
-    match convert(input, input_format, output, output_format, opt.delta) {
+#[test]
+fn positional_output() {
+    let dir = std::env::temp_dir().join(format!("srt2webvtt-positional-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("a.srt");
+    let output = dir.join("b.vtt");
+    std::fs::write(&input, "1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+
+    let mut opt = Opt::from_iter(&[Path::new("srt2webvtt"), &input, &output]);
+    opt.positional_output();
+    assert_eq!(opt.inputs, vec![input.clone()]);
+    assert_eq!(opt.output.as_ref(), Some(&output));
+    let format = guess_format(None, opt.output.as_deref(), None);
+    let nb = convert_file(
+        &opt,
+        Some(&input),
+        opt.output.as_deref(),
+        format,
+        Delta::None,
+    );
+    assert_eq!(nb, Ok(1));
+    assert_eq!(
+        std::fs::read_to_string(&output).unwrap(),
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n\n"
+    );
+
+    let mut opt = Opt::from_iter(&["srt2webvtt", "--out-dir", "out", "a.srt", "b.srt"]);
+    opt.positional_output();
+    assert_eq!(opt.inputs.len(), 2);
+    assert!(opt.output.is_none());
+
+    // An existing subtitle is an input, it's not overwritten.
+    let second = dir.join("b.srt");
+    std::fs::write(&second, "1\n00:00:03,000 --> 00:00:04,000\nWorld\n").unwrap();
+    let args = [
+        Path::new("srt2webvtt"),
+        Path::new("--output-format"),
+        Path::new("vtt"),
+        &input,
+        &second,
+    ];
+    let mut opt = Opt::from_iter(&args);
+    opt.positional_output();
+    assert_eq!(opt.inputs, vec![input.clone(), second.clone()]);
+    assert!(opt.output.is_none());
+    for input in &opt.inputs {
+        assert!(convert_to_dir(&opt, input, Format::WebVTT, Delta::None).is_ok());
+    }
+    assert_eq!(
+        std::fs::read_to_string(&second).unwrap(),
+        "1\n00:00:03,000 --> 00:00:04,000\nWorld\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(dir.join("b.vtt")).unwrap(),
+        "WEBVTT\n\n00:03.000 --> 00:04.000\nWorld\n\n"
+    );
+
+    // A missing second file is still the output.
+    let args = [Path::new("srt2webvtt"), &input, &dir.join("c.srt")];
+    let mut opt = Opt::from_iter(&args);
+    opt.positional_output();
+    assert_eq!(opt.output, Some(dir.join("c.srt")));
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[cfg(feature = "gzip")]
+#[test]
+fn gzip_convert() {
+    let dir = std::env::temp_dir().join(format!("srt2webvtt-gzip-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let input = dir.join("in.srt.gz");
+    let output = dir.join("out.vtt.gz");
+
+    let mut w = open_output(Some(&input)).unwrap();
+    w.write_all(b"1\n00:00:01,000 --> 00:00:02,000\nHello\n")
+        .unwrap();
+    w.finish().unwrap();
+
+    let opt = Opt::from_iter(&["srt2webvtt"]);
+    let format = guess_format(None, Some(&output), None);
+    assert!(matches!(format, Some(Format::WebVTT)));
+    let nb = convert_file(&opt, Some(&input), Some(&output), format, Delta::None);
+    assert_eq!(nb, Ok(1));
+
+    let mut vtt = String::new();
+    flate2::read::GzDecoder::new(File::open(&output).unwrap())
+        .read_to_string(&mut vtt)
+        .unwrap();
+    assert_eq!(vtt, "WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n\n");
+
+    // The error of the gzip trailer on a full disk is returned by finish.
+    #[cfg(unix)]
+    if Path::new("/dev/full").exists() {
+        let full = dir.join("full.vtt.gz");
+        std::os::unix::fs::symlink("/dev/full", &full).unwrap();
+        assert!(open_output(Some(&full)).unwrap().finish().is_err());
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+/// Join the `--concat` inputs, each shifted by its offset, into the output
+/// (or stdout). The cues are sorted by time.
+fn concat_files(opt: &Opt, delta: Delta) -> Result<(), ()> {
+    let output_format = get_format(opt.output_format, opt.output.as_deref(), opt.fps, "output")?;
+    let mut inputs = Vec::new();
+    for (path, offset) in opt.concat.iter() {
+        match open_input(opt, Some(path)) {
+            Ok((format, r)) => inputs.push((parse_reader(r, format), *offset)),
+            Err(err) => {
+                eprintln!("{}: {}", path.display(), err);
+                return Err(());
+            }
+        }
+    }
+    let result = open_output(opt.output.as_deref()).and_then(|mut output| {
+        convert_output_with_options(
+            concat(inputs),
+            &mut output,
+            output_format,
+            delta,
+            |cues| opt.transforms.apply(Box::new(sort_cues(cues))),
+            &opt.write_options(),
+        )
+        .and_then(|nb| output.finish().map(|_| nb))
+        .map_err(|err| err.to_string())
+    });
+    match result {
         Ok(nb) => {
-            println!("{} cues printed", nb);
+            opt.summary(&format!("{} cues printed", nb));
             Ok(())
         }
-        Err(e) => {
-            eprintln!("{}", e);
+        Err(err) => {
+            eprintln!("{}", err);
             Err(())
         }
     }
 }
 
-fn get_format(f: Option<Format>, p: &Option<PathBuf>, t: &str) -> Result<Format, ()> {
-    use std::convert::TryFrom;
-    match f.or_else(|| p.as_ref().and_then(|p| Format::try_from(p).ok())) {
+/// Print the stats of each input (or stdin) without writing any output.
+fn print_stats(opt: &Opt, delta: Delta) -> Result<(), ()> {
+    let inputs: Vec<Option<&Path>> = match opt.inputs.is_empty() {
+        true => vec![None],
+        false => opt.inputs.iter().map(|p| Some(p.as_path())).collect(),
+    };
+    let mut failed = false;
+    for input in inputs {
+        let name = input.map_or(String::from("stdin"), |p| p.display().to_string());
+        match stats_file(opt, input, delta.clone()) {
+            Ok(s) => println!("{}:\n{}", name, s),
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse and transform the input, and return its stats.
+fn stats_file(opt: &Opt, input: Option<&Path>, delta: Delta) -> Result<Stats, String> {
+    let (input_format, input) = open_input(opt, input)?;
+    let mut s = Stats::default();
+    convert_with(
+        input,
+        input_format,
+        io::sink(),
+        input_format,
+        delta,
+        |cues| {
+            s = stats(opt.transforms.apply(cues));
+            Box::new(std::iter::empty())
+        },
+    )
+    .map_err(|err| err.to_string())?;
+    Ok(s)
+}
+
+/// Split the input (or stdin) into segments in the `--split-dir`, return the
+/// number of segments. The output format is by default the input format.
+fn split_file(opt: &Opt, window: Duration, delta: Delta) -> Result<usize, String> {
+    if opt.inputs.len() > 1 {
+        return Err(String::from("Only one input with --split"));
+    }
+    let (input_format, input) = open_input(opt, opt.inputs.first().map(PathBuf::as_path))?;
+    let output_format = opt
+        .output_format
+        .map(|f| f.with_fps(opt.fps))
+        .unwrap_or(input_format);
+    let cues = parse_reader(input, input_format)
+        .collect::<io::Result<Vec<Cue>>>()
+        .map_err(|err| err.to_string())?;
+    let cues = opt
+        .transforms
+        .apply(Box::new(cues.into_iter().map(delta.applicator())));
+
+    std::fs::create_dir_all(&opt.split_dir).map_err(|err| err.to_string())?;
+    let mut nb = 0;
+    for (i, segment) in split(drop_empty(cues), window).enumerate() {
+        let path = opt
+            .split_dir
+            .join(format!("segment{:03}.{}", i, output_format.extension()));
+        let begin = match opt.split_absolute {
+            true => Duration::ZERO,
+            false => window * i as u32,
+        };
+        let segment = segment.into_iter().map(Delta::Sub(begin).applicator());
+        let mut output = open_output(Some(&path))?;
+        write_cues(segment, &mut output, output_format, &opt.write_options())
+            .and_then(|_| output.finish())
+            .map_err(|err| format!("{}: {}", path.display(), err))?;
+        nb += 1;
+    }
+    Ok(nb)
+}
+
+/// Parse each input (or stdin) and print its number of cues, stop on the
+/// first error.
+fn validate_only(opt: &Opt) -> Result<(), ()> {
+    let inputs: Vec<Option<&Path>> = match opt.inputs.is_empty() {
+        true => vec![None],
+        false => opt.inputs.iter().map(|p| Some(p.as_path())).collect(),
+    };
+    for input in inputs {
+        let name = input.map_or(String::from("stdin"), |p| p.display().to_string());
+        let result = open_input(opt, input)
+            .and_then(|(format, r)| validate_parse(r, format).map_err(|err| err.to_string()));
+        match result {
+            Ok(nb) if !opt.quiet => println!("{}: {} cues", name, nb),
+            Ok(_) => {}
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                return Err(());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the warnings of each input (or stdin) without writing any output.
+fn check(opt: &Opt, delta: Delta) -> Result<(), ()> {
+    let inputs: Vec<Option<&Path>> = match opt.inputs.is_empty() {
+        true => vec![None],
+        false => opt.inputs.iter().map(|p| Some(p.as_path())).collect(),
+    };
+    let mut failed = false;
+    for input in inputs {
+        let name = input.map_or(String::from("stdin"), |p| p.display().to_string());
+        match check_file(opt, input, delta.clone()) {
+            Ok(warnings) => {
+                for w in warnings.iter() {
+                    eprintln!("{}: {}", name, w);
+                }
+                failed |= !warnings.is_empty();
+            }
+            Err(err) => {
+                eprintln!("{}: {}", name, err);
+                failed = true;
+            }
+        }
+    }
+    if failed {
+        Err(())
+    } else {
+        Ok(())
+    }
+}
+
+/// Parse the input and validate the cues after the delta and the transforms.
+fn check_file(opt: &Opt, input: Option<&Path>, delta: Delta) -> Result<Vec<Warning>, String> {
+    let (input_format, input) = open_input(opt, input)?;
+    let mut warnings = Vec::new();
+    convert_with(
+        input,
+        input_format,
+        io::sink(),
+        input_format,
+        delta,
+        |cues| {
+            warnings = validate(opt.transforms.apply(cues));
+            Box::new(std::iter::empty())
+        },
+    )
+    .map_err(|err| err.to_string())?;
+    Ok(warnings)
+}
+
+/// The output path of the input: in the directory (or next to the input) with
+/// the extension of the format.
+fn output_path(input: &Path, dir: Option<&Path>, format: Format) -> PathBuf {
+    let output = strip_gz(input).with_extension(format.extension());
+    #[cfg(feature = "gzip")]
+    let output = match is_gz(input) {
+        true => output.with_extension(format!("{}.gz", format.extension())),
+        false => output,
+    };
+    match (dir, output.file_name()) {
+        (Some(dir), Some(name)) => dir.join(name),
+        _ => output,
+    }
+}
+
+/// Parse a `file@offset` concatenation input.
+fn parse_concat(s: &str) -> Result<(PathBuf, Duration), String> {
+    match s.rfind('@') {
+        Some(sep) => Ok((PathBuf::from(&s[..sep]), parse_timestamp(&s[sep + 1..])?)),
+        None => Err(format!("Need a '@' in the concatenation input {:?}", s)),
+    }
+}
+
+/// Parse a step like `100ms`, `1s` or `0.5s`.
+fn parse_step(s: &str) -> Result<Duration, String> {
+    let (n, unit) = match s.strip_suffix("ms") {
+        Some(n) => (n, 0.001),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.0),
+    };
+    match n.trim().parse::<f64>() {
+        Ok(n) if n >= 0.0 && n.is_finite() => Ok(Duration::from_secs_f64(n * unit)),
+        _ => Err(format!(
+            "Invalid step {:?} (expected like '100ms' or '1s')",
+            s
+        )),
+    }
+}
+
+/// Parse a `--zero-duration` policy.
+fn parse_zero_duration(s: &str) -> Result<ZeroDurationPolicy, String> {
+    match s {
+        "keep" => Ok(ZeroDurationPolicy::Keep),
+        "drop" => Ok(ZeroDurationPolicy::Drop),
+        _ => match s.strip_prefix("extend=") {
+            Some(d) => Ok(ZeroDurationPolicy::Extend(parse_step(d)?)),
+            None => Err(format!(
+                "Invalid policy {:?} (expected 'keep', 'drop' or 'extend=500ms')",
+                s
+            )),
+        },
+    }
+}
+
+/// Parse a `--max-duration-mode`.
+fn parse_max_duration_mode(s: &str) -> Result<MaxDurationMode, String> {
+    match s {
+        "truncate" => Ok(MaxDurationMode::Truncate),
+        "split" => Ok(MaxDurationMode::Split),
+        _ => Err(format!(
+            "Invalid mode {:?} (expected 'truncate' or 'split')",
+            s
+        )),
+    }
+}
+
+/// Parse a framerate, a positive number.
+fn parse_fps(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(fps) if fps.is_finite() && fps > 0.0 => Ok(fps),
+        Ok(_) => Err(format!("The framerate {:?} must be positive", s)),
+        Err(err) => Err(format!("{} on {:?}", err, s)),
+    }
+}
+
+/// Parse a `--case` value.
+fn parse_case(s: &str) -> Result<Case, String> {
+    match s {
+        "upper" => Ok(Case::Upper),
+        "lower" => Ok(Case::Lower),
+        "sentence" => Ok(Case::Sentence),
+        _ => Err(format!(
+            "Invalid case {:?} (expected 'upper', 'lower' or 'sentence')",
+            s
+        )),
+    }
+}
+
+/// Parse a `time=delta` offset.
+fn parse_offset_after(s: &str) -> Result<(Duration, Delta), String> {
+    match s.find('=') {
+        Some(sep) => Ok((parse_timestamp(&s[..sep])?, s[sep + 1..].parse()?)),
+        None => Err(format!("Need a '=' in the offset {:?}", s)),
+    }
+}
+
+/// Parse a `from=to` replacement.
+fn parse_replace(s: &str) -> Result<(String, String), String> {
+    match s.find('=') {
+        Some(sep) => Ok((s[..sep].to_string(), s[sep + 1..].to_string())),
+        None => Err(format!("Need a '=' in the replacement {:?}", s)),
+    }
+}
+
+/// Parse a `regex=to` replacement.
+#[cfg(feature = "regex")]
+fn parse_replace_regex(s: &str) -> Result<(regex::Regex, String), String> {
+    let (from, to) = parse_replace(s)?;
+    let re = regex::Regex::new(&from).map_err(|err| err.to_string())?;
+    Ok((re, to))
+}
+
+fn get_format(
+    f: Option<Format>,
+    p: Option<&Path>,
+    fps: Option<f64>,
+    t: &str,
+) -> Result<Format, ()> {
+    match guess_format(f, p, fps) {
         Some(f) => Ok(f),
         None => {
             eprintln!("Need an format for the {}", t);
@@ -69,3 +1265,36 @@ fn get_format(f: Option<Format>, p: &Option<PathBuf>, t: &str) -> Result<Format,
         }
     }
 }
+
+/// Get the format from the flag or else from the file extension. The fps is
+/// set to the MicroDVD format.
+fn guess_format(f: Option<Format>, p: Option<&Path>, fps: Option<f64>) -> Option<Format> {
+    let from_path = |p: &Path| {
+        let p = strip_gz(p);
+        Format::from_extension(p.extension()?.to_str()?)
+    };
+    f.or_else(|| p.and_then(from_path)).map(|f| f.with_fps(fps))
+}
+
+fn get_delta(delta: Delta, anchors: &[Anchor]) -> Result<Delta, ()> {
+    match anchors {
+        [] => Ok(delta),
+        [a1, a2] if delta == Delta::None => {
+            match Resync::from_anchors(a1.from, a1.to, a2.from, a2.to) {
+                Ok(r) => Ok(Delta::Resync(r)),
+                Err(err) => {
+                    eprintln!("{}", err);
+                    Err(())
+                }
+            }
+        }
+        [_, _] => {
+            eprintln!("Only one transform is supported, use --delta or --anchor");
+            Err(())
+        }
+        _ => {
+            eprintln!("Need exactly two anchors");
+            Err(())
+        }
+    }
+}
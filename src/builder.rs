@@ -0,0 +1,219 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::*;
+
+/// A builder of a conversion pipeline. The transforms are applied in this
+/// fixed order, whatever the order of the calls:
+///
+/// 1. the delta;
+/// 2. the sort of the cues;
+/// 3. the merge of the overlapping cues;
+/// 4. the zero duration policy;
+/// 5. the minimum duration;
+/// 6. the strip of the tags;
+/// 7. the wrap of the lines;
+/// 8. the drop of the cues without text.
+///
+/// ```
+/// use srt2webvtt::{ConversionBuilder, Format};
+///
+/// let mut out: Vec<u8> = Vec::new();
+/// let nb = ConversionBuilder::new()
+///     .input_format(Format::Srt)
+///     .output_format(Format::WebVTT)
+///     .strip_tags()
+///     .run("1\n00:00:01,000 --> 00:00:02,000\n<i>Hello</i>\n".as_bytes(), &mut out)
+///     .unwrap();
+/// assert_eq!(nb, 1);
+/// assert_eq!(
+///     std::str::from_utf8(&out).unwrap(),
+///     "WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n\n"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct ConversionBuilder {
+    input_format: Option<Format>,
+    output_format: Format,
+    delta: Delta,
+    sort: bool,
+    merge_overlaps: Option<Duration>,
+    zero_duration: ZeroDurationPolicy,
+    min_duration: Option<Duration>,
+    strip_tags: bool,
+    wrap_lines: Option<usize>,
+    drop_empty: bool,
+    options: WriteOptions,
+}
+impl Default for ConversionBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+impl ConversionBuilder {
+    /// A pipeline from a detected format to WebVTT, without transform.
+    pub fn new() -> Self {
+        ConversionBuilder {
+            input_format: None,
+            output_format: Format::WebVTT,
+            delta: Delta::None,
+            sort: false,
+            merge_overlaps: None,
+            zero_duration: ZeroDurationPolicy::Keep,
+            min_duration: None,
+            strip_tags: false,
+            wrap_lines: None,
+            drop_empty: false,
+            options: WriteOptions::default(),
+        }
+    }
+    /// The input format, else it's detected from the content.
+    pub fn input_format(mut self, f: Format) -> Self {
+        self.input_format = Some(f);
+        self
+    }
+    /// The output format, default to WebVTT.
+    pub fn output_format(mut self, f: Format) -> Self {
+        self.output_format = f;
+        self
+    }
+    /// The delta applied on all the cues.
+    pub fn delta(mut self, delta: Delta) -> Self {
+        self.delta = delta;
+        self
+    }
+    /// Sort the cues by time, see `sort_cues`.
+    pub fn sort(mut self) -> Self {
+        self.sort = true;
+        self
+    }
+    /// Merge the overlapping cues, see `merge_overlaps`.
+    pub fn merge_overlaps(mut self, gap: Duration) -> Self {
+        self.merge_overlaps = Some(gap);
+        self
+    }
+    /// What to do with the cues without duration, see `zero_duration`.
+    /// Default to keep them.
+    pub fn zero_duration_policy(mut self, policy: ZeroDurationPolicy) -> Self {
+        self.zero_duration = policy;
+        self
+    }
+    /// Extend the short cues, see `min_duration`.
+    pub fn min_duration(mut self, min: Duration) -> Self {
+        self.min_duration = Some(min);
+        self
+    }
+    /// Remove the formatting tags, see `strip_tags`.
+    pub fn strip_tags(mut self) -> Self {
+        self.strip_tags = true;
+        self
+    }
+    /// Wrap the long lines, see `wrap_lines`.
+    pub fn wrap_lines(mut self, max: usize) -> Self {
+        self.wrap_lines = Some(max);
+        self
+    }
+    /// Drop the cues without text, see `drop_empty`.
+    pub fn drop_empty(mut self) -> Self {
+        self.drop_empty = true;
+        self
+    }
+    /// The options of the writer.
+    pub fn write_options(mut self, options: WriteOptions) -> Self {
+        self.options = options;
+        self
+    }
+    /// Apply the transforms, except the delta, on the cues.
+    fn apply<'a>(&self, mut cues: Cues<'a>) -> Cues<'a> {
+        if self.sort {
+            cues = Box::new(sort_cues(cues));
+        }
+        if let Some(gap) = self.merge_overlaps {
+            cues = Box::new(merge_overlaps(cues, gap));
+        }
+        if self.zero_duration != ZeroDurationPolicy::Keep {
+            cues = Box::new(zero_duration(cues, self.zero_duration));
+        }
+        if let Some(min) = self.min_duration {
+            cues = Box::new(min_duration(cues, min));
+        }
+        if self.strip_tags {
+            cues = Box::new(strip_tags(cues));
+        }
+        if let Some(max) = self.wrap_lines {
+            cues = Box::new(wrap_lines(cues, max));
+        }
+        if self.drop_empty {
+            cues = Box::new(drop_empty(cues));
+        }
+        cues
+    }
+    /// Convert the cues from the reader into the writer. Return the number of
+    /// writed cues.
+    pub fn run<R: Read, W: Write>(&self, reader: R, writer: W) -> io::Result<usize> {
+        match self.input_format {
+            Some(f) => self.run_format(reader, f, writer),
+            None => {
+                let (f, reader) = detect_format(reader)?;
+                self.run_format(reader, f, writer)
+            }
+        }
+    }
+    fn run_format<R: Read, W: Write>(
+        &self,
+        reader: R,
+        input_format: Format,
+        writer: W,
+    ) -> io::Result<usize> {
+        convert_with_options(
+            reader,
+            input_format,
+            writer,
+            self.output_format,
+            self.delta.clone(),
+            |cues| self.apply(cues),
+            &self.options,
+        )
+    }
+}
+#[test]
+fn test_builder() {
+    let input = "WEBVTT
+
+00:03.000 --> 00:06.000
+World
+
+00:01.000 --> 00:04.000
+Hello
+";
+
+    let mut out: Vec<u8> = Vec::new();
+    let nb = ConversionBuilder::new()
+        .output_format(Format::Srt)
+        .merge_overlaps(Duration::ZERO)
+        .delta(Delta::Add(Duration::new(1, 0)))
+        .sort()
+        .run(input.as_bytes(), &mut out)
+        .unwrap();
+    assert_eq!(nb, 1);
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1\n00:00:02,000 --> 00:00:07,000\nHello\nWorld\n\n"
+    );
+
+    let mut out: Vec<u8> = Vec::new();
+    let nb = ConversionBuilder::new()
+        .zero_duration_policy(ZeroDurationPolicy::Drop)
+        .run(
+            "WEBVTT\n\n00:01.000 --> 00:01.000\nHello\n".as_bytes(),
+            &mut out,
+        )
+        .unwrap();
+    assert_eq!(nb, 0);
+
+    let input = "WEBVTT\n\nchapter\n00:00.000 --> 00:01.000\n\n00:01.000 --> 00:02.000\nHello\n";
+    let run = |builder: ConversionBuilder| builder.run(input.as_bytes(), Vec::new()).unwrap();
+    assert_eq!(run(ConversionBuilder::new()), 2);
+    assert_eq!(run(ConversionBuilder::new().drop_empty()), 1);
+}
@@ -0,0 +1,167 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use std::fmt;
+use std::io::{self, ErrorKind};
+
+/// An error from a parser. The parsers yield it into an `io::Error`, get it
+/// back with `io::Error::get_ref()` and `downcast_ref::<ParseError>()`.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file does not begin with the format header.
+    MissingHeader,
+    /// A time code can not be parsed.
+    BadTimestamp {
+        /// The line number, from 1.
+        line: usize,
+        /// The byte offset of the line begin, if known.
+        offset: Option<u64>,
+        /// The full text of the line, if known.
+        raw: Option<String>,
+        reason: String,
+    },
+    /// A line is not expected here.
+    UnexpectedLine {
+        /// The line number, from 1.
+        line: usize,
+        /// The byte offset of the line begin, if known.
+        offset: Option<u64>,
+        /// The full text of the line, if known.
+        raw: Option<String>,
+        content: String,
+    },
+    /// The stream ends in the middle of a cue.
+    UnexpectedEof {
+        line: usize,
+        /// The byte offset of the line begin, if known.
+        offset: Option<u64>,
+    },
+    Io(io::Error),
+}
+impl ParseError {
+    /// Create an io::Result with a BadTimestamp error.
+    pub(crate) fn timestamp<T>(
+        because: impl fmt::Display,
+        data: &str,
+        line: usize,
+    ) -> io::Result<T> {
+        Err(ParseError::BadTimestamp {
+            line,
+            offset: None,
+            raw: None,
+            reason: format!("{} in {:?}", because, data),
+        }
+        .into())
+    }
+    /// Create an io::Result with an UnexpectedLine error.
+    pub(crate) fn unexpected<T>(content: &str, line: usize) -> io::Result<T> {
+        Err(ParseError::UnexpectedLine {
+            line,
+            offset: None,
+            raw: None,
+            content: content.to_string(),
+        }
+        .into())
+    }
+    /// Set the unknown byte offset and raw line from the line number with
+    /// line_of.
+    pub(crate) fn locate(&mut self, line_of: impl Fn(usize) -> Option<(u64, String)>) {
+        match self {
+            ParseError::BadTimestamp {
+                line, offset, raw, ..
+            }
+            | ParseError::UnexpectedLine {
+                line, offset, raw, ..
+            } => {
+                if let Some((o, r)) = line_of(*line) {
+                    offset.get_or_insert(o);
+                    raw.get_or_insert(r);
+                }
+            }
+            ParseError::UnexpectedEof { line, offset } if offset.is_none() => {
+                *offset = line_of(*line).map(|(o, _)| o);
+            }
+            _ => {}
+        }
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::MissingHeader => write!(f, "Need a format header line"),
+            ParseError::BadTimestamp { reason, .. } => write!(f, "{} ({})", reason, Position(self)),
+            ParseError::UnexpectedLine { content, .. } => {
+                write!(f, "Unexpected line {:?} ({})", content, Position(self))
+            }
+            ParseError::UnexpectedEof { .. } => {
+                write!(f, "Unexpected end of the cue ({})", Position(self))
+            }
+            ParseError::Io(e) => e.fmt(f),
+        }
+    }
+}
+
+/// Display the line and the byte offset of an error.
+struct Position<'a>(&'a ParseError);
+impl fmt::Display for Position<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            ParseError::BadTimestamp { line, offset, .. }
+            | ParseError::UnexpectedLine { line, offset, .. }
+            | ParseError::UnexpectedEof { line, offset } => match offset {
+                Some(offset) => write!(f, "line {}, byte {}", line, offset),
+                None => write!(f, "line {}", line),
+            },
+            _ => Ok(()),
+        }
+    }
+}
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
+    }
+}
+impl From<ParseError> for io::Error {
+    fn from(e: ParseError) -> Self {
+        match e {
+            ParseError::Io(e) => e,
+            ParseError::UnexpectedEof { .. } => io::Error::new(ErrorKind::UnexpectedEof, e),
+            e => io::Error::new(ErrorKind::InvalidData, e),
+        }
+    }
+}
+#[test]
+fn parse_error_into_io() {
+    let e: io::Error = ParseError::BadTimestamp {
+        line: 3,
+        offset: None,
+        raw: None,
+        reason: String::from("Invalid duration syntax in \"12\""),
+    }
+    .into();
+    assert_eq!(e.kind(), ErrorKind::InvalidData);
+    assert_eq!(e.to_string(), "Invalid duration syntax in \"12\" (line 3)");
+    match e.get_ref().unwrap().downcast_ref::<ParseError>() {
+        Some(ParseError::BadTimestamp { line: 3, .. }) => {}
+        other => panic!("Unexpected error {:?}", other),
+    }
+
+    let mut e = ParseError::UnexpectedEof {
+        line: 3,
+        offset: None,
+    };
+    e.locate(|line| Some((line as u64 * 10, String::new())));
+    assert_eq!(e.to_string(), "Unexpected end of the cue (line 3, byte 30)");
+
+    let e: io::Error = ParseError::Io(io::Error::other("io")).into();
+    assert_eq!(e.kind(), ErrorKind::Other);
+}
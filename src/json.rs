@@ -0,0 +1,205 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, Format, SubtitleParser, WriteOptions};
+use serde::{Deserialize, Serialize};
+use std::io::{self, Read, Write};
+use std::time::Duration;
+
+/// A cue in the JSON format, with the times as milliseconds.
+#[derive(Serialize, Deserialize)]
+struct JsonCue {
+    id: Option<String>,
+    begin_ms: u64,
+    end_ms: u64,
+    text: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    coordinates: Option<String>,
+}
+impl From<Cue> for JsonCue {
+    fn from(c: Cue) -> Self {
+        JsonCue {
+            id: c.id,
+            begin_ms: c.begin.as_millis() as u64,
+            end_ms: c.end.as_millis() as u64,
+            text: c.text,
+            settings: c.settings,
+            coordinates: c.coordinates,
+        }
+    }
+}
+
+/// A parser of a JSON array of cues, like writed by `out`.
+pub struct JsonParser {
+    cues: std::iter::Enumerate<std::vec::IntoIter<JsonCue>>,
+}
+impl JsonParser {
+    /// Read and parse all the JSON array. The error of a malformed JSON
+    /// gives the byte offset.
+    pub fn new<R: Read>(mut r: R) -> io::Result<Self> {
+        let mut s = String::new();
+        r.read_to_string(&mut s)?;
+        let cues: Vec<JsonCue> = serde_json::from_str(&s).map_err(|e| {
+            let offset: usize = s
+                .split_inclusive('\n')
+                .take(e.line().saturating_sub(1))
+                .map(str::len)
+                .sum::<usize>()
+                + e.column().saturating_sub(1);
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Invalid JSON at byte {}: {}", offset, e),
+            )
+        })?;
+        Ok(Self {
+            cues: cues.into_iter().enumerate(),
+        })
+    }
+}
+impl SubtitleParser for JsonParser {
+    fn format(&self) -> Format {
+        Format::Json
+    }
+}
+impl Iterator for JsonParser {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        let (i, c) = self.cues.next()?;
+        if c.end_ms < c.begin_ms {
+            return Some(Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("The cue {} ends before its begin", i + 1),
+            )));
+        }
+        Some(Ok(Cue {
+            id: c.id,
+            begin: Duration::from_millis(c.begin_ms),
+            end: Duration::from_millis(c.end_ms),
+            text: c.text,
+            settings: c.settings,
+            coordinates: c.coordinates,
+        }))
+    }
+}
+#[test]
+fn jsonparser() {
+    let mut p = JsonParser::new(
+        r#"[{"id": "intro", "begin_ms": 1000, "end_ms": 4500, "text": ["Hello"]}]"#.as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            Some(String::from("intro")),
+            Duration::new(1, 0),
+            Duration::new(4, 500_000_000),
+            vec![String::from("Hello")],
+        )
+    );
+    assert!(p.next().is_none());
+
+    let mut p = JsonParser::new(
+        r#"[{"id": null, "begin_ms": 2000, "end_ms": 1000, "text": []}]"#.as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(
+        p.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+
+    let e = JsonParser::new("[\n{\"id\": }]".as_bytes()).err().unwrap();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(
+        e.to_string().starts_with("Invalid JSON at byte 9:"),
+        "{}",
+        e
+    );
+}
+
+/// Write all Cues from the input Iterator into the write W, as a JSON array
+/// indented with two spaces. Return the number fo writed cue.
+pub fn out<I, W>(cues: I, w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    out_with(cues, w, &WriteOptions::default())
+}
+
+/// Like `out` with the writing options: `compact` writes the JSON without
+/// whitespace.
+pub fn out_with<I, W>(cues: I, w: W, options: &WriteOptions) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut w = options.line_ending.writer(w);
+    let cues: Vec<JsonCue> = cues.map(JsonCue::from).collect();
+    if options.compact {
+        serde_json::to_writer(&mut w, &cues)?;
+    } else {
+        serde_json::to_writer_pretty(&mut w, &cues)?;
+    }
+    writeln!(w)?;
+    Ok(cues.len())
+}
+#[test]
+fn test_out() {
+    let cues = vec![
+        Cue::new(
+            Some(String::from("intro")),
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        ),
+        Cue::new(
+            None,
+            Duration::new(5, 0),
+            Duration::new(9, 500_000_000),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die."),
+            ],
+        ),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out(cues.clone().into_iter(), &mut output).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        r#"[
+  {
+    "id": "intro",
+    "begin_ms": 1000,
+    "end_ms": 4000,
+    "text": [
+      "Never drink liquid nitrogen."
+    ]
+  },
+  {
+    "id": null,
+    "begin_ms": 5000,
+    "end_ms": 9500,
+    "text": [
+      "— It will perforate your stomach.",
+      "— You could die."
+    ]
+  }
+]
+"#
+    );
+
+    let options = WriteOptions {
+        compact: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    out_with(cues.into_iter().take(1), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "[{\"id\":\"intro\",\"begin_ms\":1000,\"end_ms\":4000,\"text\":[\"Never drink liquid nitrogen.\"]}]\n"
+    );
+}
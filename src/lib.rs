@@ -2,58 +2,321 @@
 // Use of this source code is governed by a BSD
 // license that can be found in the LICENSE file.
 
+use std::fmt;
 use std::io;
-use std::io::{BufRead, BufReader, Lines, Read, Write};
-use std::path::PathBuf;
+use std::io::{BufRead, BufReader, Chain, Cursor, Read, Write};
+use std::path::{Path, PathBuf};
 use std::str::FromStr;
 use std::time::Duration;
 
+mod builder;
+pub use builder::ConversionBuilder;
+
+mod error;
+pub use error::ParseError;
+
+mod ass;
+pub use ass::out as ass_out;
+pub use ass::AssParser;
+
+#[cfg(feature = "serde")]
+mod json;
+#[cfg(feature = "serde")]
+pub use json::out as json_out;
+#[cfg(feature = "serde")]
+pub use json::out_with as json_out_with;
+#[cfg(feature = "serde")]
+pub use json::JsonParser;
+
+mod lrc;
+pub use lrc::out as lrc_out;
+pub use lrc::out_with as lrc_out_with;
+pub use lrc::LrcParser;
+
+mod microdvd;
+pub use microdvd::out as microdvd_out;
+pub use microdvd::MicroDvdParser;
+
+mod sami;
+pub use sami::SamiParser;
+
+mod sbv;
+pub use sbv::out as sbv_out;
+pub use sbv::SbvParser;
+
 mod srt;
 pub use srt::out as srt_out;
+pub use srt::out_numbered as srt_out_numbered;
+pub use srt::out_with as srt_out_with;
 pub use srt::SrtParser;
 
 mod webvtt;
 pub use webvtt::out as webvtt_out;
-pub use webvtt::WebVTTParser;
+pub use webvtt::out_with as webvtt_out_with;
+pub use webvtt::WebVTTTimestamp;
+pub use webvtt::{BlankLinePolicy, Region, WebVTTParser};
 
-/// One cue.
+mod text;
+pub use text::out as text_out;
+pub use text::out_with as text_out_with;
+
+mod tags;
+pub use tags::{CueLine, TagStyle};
+
+mod transform;
+#[cfg(feature = "regex")]
+pub use transform::replace_regex;
+pub use transform::{
+    dedupe, delta_after, delta_to_end, drop_empty, enforce_gap, enforce_gap_with, filter_text,
+    gaps, join_lines, max_duration, max_lines, merge_overlaps, merge_tracks, min_duration,
+    normalize_typography, replace_text, round_times, round_to_frames, shift_first_to, sort_cues,
+    split, strip_empty_lines, strip_tags, tail, transform_case, trim_to_next, truncate_times,
+    window, wrap_lines, zero_duration, Case, MaxDurationMode, Typography, ZeroDurationPolicy,
+};
+
+mod validate;
+pub use validate::{cps, has_sub_millis, stats, validate, Stats, Warning};
+
+/// One cue. The fields are public, but prefer the methods: `set_times` keeps
+/// the begin before the end, like `new`.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Cue {
     pub id: Option<String>,
+    /// Serialized as milliseconds.
+    #[cfg_attr(feature = "serde", serde(with = "duration_ms"))]
     pub begin: Duration,
+    /// Serialized as milliseconds.
+    #[cfg_attr(feature = "serde", serde(with = "duration_ms"))]
     pub end: Duration,
     pub text: Vec<String>,
+    /// The WebVTT cue settings, like `line:63% position:72% align:start`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub settings: Option<String>,
+    /// The SRT coordinates after the time code, like `X1:100 X2:200 Y1:300
+    /// Y2:400`. Only writed in SRT.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub coordinates: Option<String>,
 }
 impl Cue {
     /// Create a new cue.
     pub fn new(id: Option<String>, begin: Duration, end: Duration, t: Vec<String>) -> Cue {
-        if begin > end {
-            Cue {
-                id: id,
-                begin: end,
-                end: begin,
-                text: t,
-            }
-        } else {
-            Cue {
-                id: id,
-                begin: begin,
-                end: end,
-                text: t,
-            }
+        let mut c = Cue {
+            id,
+            begin,
+            end,
+            text: t,
+            settings: None,
+            coordinates: None,
+        };
+        c.set_times(begin, end);
+        c
+    }
+    /// The id of the cue, if any.
+    pub fn id(&self) -> Option<&str> {
+        self.id.as_deref()
+    }
+    /// The begin of the cue.
+    pub fn begin(&self) -> Duration {
+        self.begin
+    }
+    /// The end of the cue, never before the begin if set by `new` or
+    /// `set_times`.
+    pub fn end(&self) -> Duration {
+        self.end
+    }
+    /// The text lines of the cue.
+    pub fn text(&self) -> &[String] {
+        &self.text
+    }
+    /// Set the begin and the end of the cue, they are swapped if the begin is
+    /// after the end.
+    pub fn set_times(&mut self, begin: Duration, end: Duration) {
+        self.begin = begin.min(end);
+        self.end = begin.max(end);
+    }
+    /// The display duration of the cue.
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.begin)
+    }
+    /// Return true if the two cues are displayed at the same time. The time
+    /// ranges are half-open, so a cue ending when the other begins does not
+    /// overlap it.
+    pub fn overlaps(&self, other: &Cue) -> bool {
+        self.begin < other.end && other.begin < self.end
+    }
+    /// Detect the format of the bytes, see `detect_format`, and parse all the
+    /// cues. An empty or unknown input is an `InvalidData` error which names
+    /// the tried formats.
+    ///
+    /// ```
+    /// let cues = srt2webvtt::Cue::parse_auto(b"WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n").unwrap();
+    /// assert_eq!(cues[0].text, vec![String::from("Hello")]);
+    /// ```
+    pub fn parse_auto(bytes: &[u8]) -> io::Result<Vec<Cue>> {
+        let (format, r) = detect_format(bytes)?;
+        parse_reader(r, format).collect()
+    }
+    /// The text lines with the WebVTT voice spans parsed.
+    pub fn lines(&self) -> Vec<CueLine> {
+        self.text.iter().map(|l| CueLine::parse(l)).collect()
+    }
+}
+#[test]
+fn cue_parse_auto() {
+    let cues = Cue::parse_auto(b"1\n00:00:01,000 --> 00:00:02,000\nHello\n").unwrap();
+    assert_eq!(
+        cues,
+        vec![Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(2, 0),
+            vec![String::from("Hello")]
+        )]
+    );
+    for bad in [&b""[..], b"Hello\nWorld\n"] {
+        let err = Cue::parse_auto(bad).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().contains("WEBVTT"));
+    }
+}
+#[test]
+fn cue_set_times() {
+    let mut c = Cue::new(
+        Some(String::from("intro")),
+        Duration::new(5, 0),
+        Duration::new(2, 0),
+        vec![String::from("Hello")],
+    );
+    assert_eq!(
+        (c.begin(), c.end()),
+        (Duration::new(2, 0), Duration::new(5, 0))
+    );
+    assert_eq!(c.id(), Some("intro"));
+    assert_eq!(c.text(), &[String::from("Hello")]);
+
+    c.set_times(Duration::new(9, 0), Duration::new(7, 0));
+    assert_eq!(
+        (c.begin(), c.end()),
+        (Duration::new(7, 0), Duration::new(9, 0))
+    );
+    c.set_times(Duration::new(3, 0), Duration::new(4, 0));
+    assert_eq!(
+        (c.begin(), c.end()),
+        (Duration::new(3, 0), Duration::new(4, 0))
+    );
+    assert!(c.begin() <= c.end());
+}
+#[test]
+fn cue_duration_overlaps() {
+    fn cue(begin: u64, end: u64) -> Cue {
+        Cue::new(
+            None,
+            Duration::new(begin, 0),
+            Duration::new(end, 0),
+            Vec::new(),
+        )
+    }
+
+    assert_eq!(cue(2, 5).duration(), Duration::new(3, 0));
+    assert_eq!(cue(5, 5).duration(), Duration::ZERO);
+
+    assert!(cue(0, 5).overlaps(&cue(4, 8)));
+    assert!(cue(4, 8).overlaps(&cue(0, 5)));
+    assert!(cue(0, 10).overlaps(&cue(2, 3)));
+    assert!(!cue(0, 5).overlaps(&cue(5, 8)));
+    assert!(!cue(0, 5).overlaps(&cue(6, 8)));
+
+    assert!(cue(0, 10).overlaps(&cue(5, 5)));
+    assert!(!cue(5, 5).overlaps(&cue(5, 5)));
+    assert!(!cue(0, 5).overlaps(&cue(5, 5)));
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn cue_serde() {
+    let c = Cue::new(
+        None,
+        Duration::new(1, 0),
+        Duration::new(4, 0),
+        vec![String::from("Hello")],
+    );
+    let json = r#"{"id":null,"begin":1000,"end":4000,"text":["Hello"]}"#;
+    assert_eq!(serde_json::to_string(&c).unwrap(), json);
+    assert_eq!(serde_json::from_str::<Cue>(json).unwrap(), c);
+}
+
+/// (De)serialize a Duration as an integer of milliseconds.
+#[cfg(feature = "serde")]
+mod duration_ms {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(d: &Duration, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u64(d.as_millis() as u64)
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Duration, D::Error> {
+        Ok(Duration::from_millis(u64::deserialize(d)?))
+    }
+}
+
+/// Display the cue like in a WebVTT file, without the final blank line.
+impl std::fmt::Display for Cue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        if let Some(id) = &self.id {
+            writeln!(f, "{}", id)?;
+        }
+        write!(
+            f,
+            "{} --> {}",
+            WebVTTTimestamp(self.begin),
+            WebVTTTimestamp(self.end)
+        )?;
+        if let Some(settings) = &self.settings {
+            write!(f, " {}", settings)?;
         }
+        for l in &self.text {
+            write!(f, "\n{}", l)?;
+        }
+        Ok(())
     }
 }
+#[test]
+fn cue_display() {
+    let c = Cue::new(
+        Some(String::from("identifier")),
+        Duration::new(5, 0),
+        Duration::new(3609, 500_000_000),
+        vec![String::from("Hello"), String::from("World")],
+    );
+    assert_eq!(
+        c.to_string(),
+        "identifier
+00:05.000 --> 01:00:09.500
+Hello
+World"
+    );
+}
 
 /// A delta duration to apply on a cue's time code.
 #[derive(Clone, Debug, PartialEq)]
 pub enum Delta {
     Add(Duration),
     Sub(Duration),
+    /// Multiply the time codes by the factor, for a framerate change.
+    Scale(f64),
+    /// A linear retiming solved from two anchors.
+    Resync(Resync),
     None,
 }
 impl Delta {
-    /// Apply the delta time to the cue.
+    /// Apply the delta time to the cue. A subtraction is clamped to zero.
     pub fn apply(&self, c: &mut Cue) {
         match self {
             Delta::Add(d) => {
@@ -61,32 +324,22 @@ impl Delta {
                 c.end += *d;
             }
             Delta::Sub(d) => {
-                c.begin -= *d;
-                c.end -= *d;
+                c.begin = c.begin.checked_sub(*d).unwrap_or(Duration::ZERO);
+                c.end = c.end.checked_sub(*d).unwrap_or(Duration::ZERO);
             }
+            Delta::Scale(f) => {
+                c.begin = c.begin.mul_f64(*f);
+                c.end = c.end.mul_f64(*f);
+            }
+            Delta::Resync(r) => r.apply(c),
             Delta::None => {}
         }
     }
     /// A closure to apply the delta time on a Cue. Use it with Iterator.map()
     pub fn applicator(&self) -> impl Fn(Cue) -> Cue {
-        fn add(c: &mut Cue, d: &Duration) {
-            c.begin += *d;
-            c.end += *d;
-        }
-        fn sub(c: &mut Cue, d: &Duration) {
-            c.begin -= *d;
-            c.end -= *d;
-        }
-        fn zero(_: &mut Cue, _: &Duration) {}
-
-        let (f, d): (fn(&mut Cue, &Duration), Duration) = match self {
-            Delta::Add(d) => (add, *d),
-            Delta::Sub(d) => (sub, *d),
-            Delta::None => (zero, Duration::new(0, 0)),
-        };
-
+        let delta = self.clone();
         move |mut c: Cue| {
-            f(&mut c, &d);
+            delta.apply(&mut c);
             c
         }
     }
@@ -117,6 +370,18 @@ fn delta_apply() {
         cc,
         Cue::new(None, Duration::new(3, 10), Duration::new(4, 20), Vec::new())
     );
+
+    let mut cc = c.clone();
+    Delta::Scale(2.0).apply(&mut cc);
+    assert_eq!(
+        cc,
+        Cue::new(
+            None,
+            Duration::new(10, 20),
+            Duration::new(12, 40),
+            Vec::new()
+        )
+    );
 }
 #[test]
 fn delta_applicator() {
@@ -142,41 +407,41 @@ fn delta_applicator() {
         Cue::new(None, Duration::new(3, 10), Duration::new(4, 20), Vec::new())
     );
 }
+#[test]
+fn delta_sub_saturating() {
+    let c = Cue::new(None, Duration::new(3, 0), Duration::new(5, 0), Vec::new());
+    let zero = Cue::new(None, Duration::ZERO, Duration::ZERO, Vec::new());
+    let delta = Delta::Sub(Duration::new(10, 0));
+
+    let mut cc = c.clone();
+    delta.apply(&mut cc);
+    assert_eq!(cc, zero);
+
+    assert_eq!(delta.applicator()(c), zero);
+}
 impl FromStr for Delta {
     type Err = String;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s == "" || s == "0" {
+        if s.is_empty() || s == "0" {
             return Ok(Delta::None);
         }
 
-        let sign: char = s.chars().next().unwrap();
-        let s = &s[1..];
-
-        let begin = s.find(':');
-        let min: f64 = match begin {
-            Some(sep) => s[..sep]
-                .parse::<u64>()
-                .map_err(|err| format!("{} on {:?}", err, s))? as f64,
-            None => 0.0,
-        } * 60.0;
-        let s = match begin {
-            Some(sep) => &s[(sep + 1)..],
-            None => s,
-        };
-
-        let f: f64 = s.parse().map_err(|err| format!("{} on {:?}", err, s))?;
-        let d = Duration::from_secs_f64(min + f);
-
-        Ok(match sign {
-            '+' => Delta::Add(d),
-            '-' => Delta::Sub(d),
-            _ => {
-                return Err(format!(
-                    "Need a sign at begin to a Delta time ({:?}) or zero or an empty string",
-                    s
-                ))
+        if let Some(f) = s.strip_prefix('*') {
+            let f: f64 = f.parse().map_err(|err| format!("{} on {:?}", err, f))?;
+            if !f.is_finite() || f <= 0.0 {
+                return Err(format!("The scale factor {:?} must be positive", f));
             }
-        })
+            return Ok(Delta::Scale(f));
+        }
+
+        match s.chars().next() {
+            Some('+') => Ok(Delta::Add(parse_timestamp(&s[1..])?)),
+            Some('-') => Ok(Delta::Sub(parse_timestamp(&s[1..])?)),
+            _ => Err(format!(
+                "Need a sign or a star at begin to a Delta time ({:?}) or zero or an empty string",
+                s
+            )),
+        }
     }
 }
 #[test]
@@ -189,24 +454,274 @@ fn delta_fromstr() {
     assert_eq!("-96.125".parse::<Delta>().unwrap(), sub);
     assert_eq!("-1:36.125".parse::<Delta>().unwrap(), sub);
 
+    assert_eq!(
+        "+1:02:03.5".parse::<Delta>().unwrap(),
+        Delta::Add(Duration::new(3723, 500_000_000))
+    );
+    assert_eq!(
+        "+1:02:03:04".parse::<Delta>().unwrap_err(),
+        "Too many ':' in \"1:02:03:04\" (expected h:m:s, m:s or s)"
+    );
+
     assert_eq!("".parse::<Delta>().unwrap(), Delta::None);
     assert_eq!("0".parse::<Delta>().unwrap(), Delta::None);
+
+    assert_eq!("*1.04271".parse::<Delta>().unwrap(), Delta::Scale(1.04271));
+    assert!("*-1".parse::<Delta>().is_err());
+}
+
+/// A linear retiming `t' = scale * t ± offset`, solved from two anchors.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Resync {
+    pub scale: f64,
+    pub offset: Duration,
+    /// If true, the offset is subtracted.
+    pub negative: bool,
+}
+impl Resync {
+    /// Solve the linear map where the time t1 become t1p and t2 become t2p.
+    pub fn from_anchors(
+        t1: Duration,
+        t1p: Duration,
+        t2: Duration,
+        t2p: Duration,
+    ) -> Result<Self, String> {
+        if t1 == t2 {
+            return Err(format!("The two anchors have the same time {:?}", t1));
+        }
+
+        let scale = (t2p.as_secs_f64() - t1p.as_secs_f64()) / (t2.as_secs_f64() - t1.as_secs_f64());
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(String::from("The anchors reverse the time order"));
+        }
+
+        let offset = t1p.as_secs_f64() - scale * t1.as_secs_f64();
+        Ok(Resync {
+            scale,
+            offset: Duration::from_secs_f64(offset.abs()),
+            negative: offset < 0.0,
+        })
+    }
+    /// Apply the retiming to the cue. A negative time is clamped to zero.
+    pub fn apply(&self, c: &mut Cue) {
+        c.begin = self.map(c.begin);
+        c.end = self.map(c.end);
+    }
+    fn map(&self, d: Duration) -> Duration {
+        let d = d.mul_f64(self.scale);
+        if self.negative {
+            d.checked_sub(self.offset).unwrap_or(Duration::ZERO)
+        } else {
+            d + self.offset
+        }
+    }
+}
+#[test]
+fn resync_from_anchors() {
+    fn sec(s: u64) -> Duration {
+        Duration::new(s, 0)
+    }
+    fn resync(t1: u64, t1p: u64, t2: u64, t2p: u64) -> Resync {
+        Resync::from_anchors(sec(t1), sec(t1p), sec(t2), sec(t2p)).unwrap()
+    }
+
+    let mut c = Cue::new(None, sec(10), sec(20), Vec::new());
+    resync(10, 20, 20, 40).apply(&mut c);
+    assert_eq!(c, Cue::new(None, sec(20), sec(40), Vec::new()));
+
+    let mut c = Cue::new(None, sec(10), sec(20), Vec::new());
+    resync(10, 15, 20, 25).apply(&mut c);
+    assert_eq!(c, Cue::new(None, sec(15), sec(25), Vec::new()));
+
+    let mut c = Cue::new(None, sec(2), sec(20), Vec::new());
+    resync(10, 5, 20, 15).apply(&mut c);
+    assert_eq!(c, Cue::new(None, sec(0), sec(15), Vec::new()));
+
+    let r = resync(60, 62, 3600, 3630);
+    let mut c = Cue::new(None, sec(60), sec(3600), Vec::new());
+    r.apply(&mut c);
+    assert!((c.begin.as_secs_f64() - 62.0).abs() < 0.001);
+    assert!((c.end.as_secs_f64() - 3630.0).abs() < 0.001);
+
+    assert!(Resync::from_anchors(sec(1), sec(2), sec(1), sec(3)).is_err());
+    assert!(Resync::from_anchors(sec(1), sec(3), sec(2), sec(1)).is_err());
+}
+
+/// A known correction: the time `from` must become `to`.
+/// Parsed from `from=to`, like `00:01:00=00:01:02`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Anchor {
+    pub from: Duration,
+    pub to: Duration,
+}
+impl FromStr for Anchor {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.find('=') {
+            Some(sep) => Ok(Anchor {
+                from: parse_timestamp(&s[..sep])?,
+                to: parse_timestamp(&s[sep + 1..])?,
+            }),
+            None => Err(format!("Need a '=' in the anchor {:?}", s)),
+        }
+    }
+}
+#[test]
+fn anchor_fromstr() {
+    assert_eq!(
+        "00:01:00=1:02.5".parse::<Anchor>().unwrap(),
+        Anchor {
+            from: Duration::new(60, 0),
+            to: Duration::new(62, 500_000_000),
+        }
+    );
+    assert!("00:01:00".parse::<Anchor>().is_err());
+}
+
+/// Parse a time like `h:m:s.frac`, `m:s.frac` or `s.frac`.
+pub fn parse_timestamp(s: &str) -> Result<Duration, String> {
+    let split: Vec<&str> = s.trim().split(':').collect();
+    if split.len() > 3 {
+        return Err(format!(
+            "Too many ':' in {:?} (expected h:m:s, m:s or s)",
+            s
+        ));
+    }
+
+    let mut secs: f64 = 0.0;
+    for part in &split[..split.len() - 1] {
+        secs = (secs
+            + part
+                .parse::<u64>()
+                .map_err(|err| format!("{} on {:?}", err, s))? as f64)
+            * 60.0;
+    }
+    let f: f64 = split[split.len() - 1]
+        .parse()
+        .map_err(|err| format!("{} on {:?}", err, s))?;
+    if !f.is_finite() || f < 0.0 {
+        return Err(format!("Invalid seconds in {:?}", s));
+    }
+
+    Ok(Duration::from_secs_f64(secs + f))
+}
+#[test]
+fn test_parse_timestamp() {
+    assert_eq!(
+        parse_timestamp("1:02:03.5").unwrap(),
+        Duration::new(3723, 500_000_000)
+    );
+    assert_eq!(
+        parse_timestamp("1:36.125").unwrap(),
+        Duration::new(96, 125_000_000)
+    );
+    assert_eq!(parse_timestamp("12").unwrap(), Duration::new(12, 0));
+    assert!(parse_timestamp("1:2:3:4").is_err());
 }
 
 /// The crate supported formats for input or output stream.
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum Format {
     WebVTT,
     Srt,
+    Ass,
+    Sbv,
+    /// MicroDVD with its framerate, needed to convert the frames.
+    MicroDvd(Option<f64>),
+    /// A plain text transcript, only for the output.
+    Text,
+    /// A JSON array of cues, with the `serde` feature.
+    Json,
+    /// The LRC lyrics, the end of a cue is the begin of the next one.
+    Lrc,
+    /// The SAMI (`.smi`) HTML subtitles, only for the input.
+    Sami,
+}
+impl Format {
+    /// The file extension of the format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::WebVTT => "vtt",
+            Format::Srt => "srt",
+            Format::Ass => "ass",
+            Format::Sbv => "sbv",
+            Format::MicroDvd(_) => "sub",
+            Format::Text => "txt",
+            Format::Json => "json",
+            Format::Lrc => "lrc",
+            Format::Sami => "smi",
+        }
+    }
+    /// Get the format of a file extension without the dot, like `vtt` or
+    /// `WebVTT`, the case is ignored. The MicroDVD format is without
+    /// framerate. The TTML (`.ttml` and `.dfxp`) is not supported.
+    pub fn from_extension(ext: &str) -> Option<Format> {
+        match ext.to_ascii_lowercase().as_str() {
+            "vtt" | "webvtt" => Some(Format::WebVTT),
+            "srt" => Some(Format::Srt),
+            "ass" | "ssa" => Some(Format::Ass),
+            "sbv" => Some(Format::Sbv),
+            "sub" => Some(Format::MicroDvd(None)),
+            "txt" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "lrc" => Some(Format::Lrc),
+            "smi" | "sami" => Some(Format::Sami),
+            _ => None,
+        }
+    }
+    /// Set the framerate of a MicroDVD format without one.
+    pub fn with_fps(self, fps: Option<f64>) -> Self {
+        match self {
+            Format::MicroDvd(None) => Format::MicroDvd(fps),
+            f => f,
+        }
+    }
+    /// The error for a format used without its feature.
+    #[cfg(not(feature = "serde"))]
+    fn feature_error(self, feature: &str) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The {} format needs the {:?} feature", self, feature),
+        )
+    }
+    /// The error for an output only format used as input.
+    fn input_error(self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The {} format is only an output format", self),
+        )
+    }
+    /// The error for an input only format used as output.
+    fn output_error(self) -> io::Error {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("The {} format is only an input format", self),
+        )
+    }
+    /// Get the framerate of a MicroDVD format, or an error.
+    fn fps(self) -> io::Result<f64> {
+        match self {
+            Format::MicroDvd(Some(fps)) => Ok(fps),
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Need a framerate for the MicroDVD format",
+            )),
+        }
+    }
 }
 impl std::convert::TryFrom<&PathBuf> for Format {
     type Error = ();
     fn try_from(p: &PathBuf) -> Result<Self, Self::Error> {
-        match p.extension() {
-            Some(ext) if ext == "vtt" => Ok(Format::WebVTT),
-            Some(ext) if ext == "srt" => Ok(Format::Srt),
-            _ => Err(()),
-        }
+        Self::try_from(p.as_path())
+    }
+}
+impl std::convert::TryFrom<&Path> for Format {
+    type Error = ();
+    fn try_from(p: &Path) -> Result<Self, Self::Error> {
+        p.extension()
+            .and_then(|ext| ext.to_str())
+            .and_then(Format::from_extension)
+            .ok_or(())
     }
 }
 impl FromStr for Format {
@@ -215,13 +730,627 @@ impl FromStr for Format {
         match s {
             "srt" => Ok(Format::Srt),
             "vtt" | "webvtt" => Ok(Format::WebVTT),
+            "ass" | "ssa" => Ok(Format::Ass),
+            "sbv" => Ok(Format::Sbv),
+            "sub" | "microdvd" => Ok(Format::MicroDvd(None)),
+            "txt" | "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "lrc" => Ok(Format::Lrc),
+            "smi" | "sami" => Ok(Format::Sami),
             _ => Err(format!(
-                "Unknown format for {:?} (possible value are: 'vtt' and 'srt')",
+                "Unknown format for {:?} (possible value are: 'vtt', 'srt', 'ass', 'sbv', 'sub', 'txt', 'json', 'lrc' and 'smi')",
                 s
             )),
         }
     }
 }
+#[test]
+fn test_format_from_extension() {
+    for (ext, format) in [
+        ("vtt", Some(Format::WebVTT)),
+        ("webvtt", Some(Format::WebVTT)),
+        ("VTT", Some(Format::WebVTT)),
+        ("srt", Some(Format::Srt)),
+        ("SRT", Some(Format::Srt)),
+        ("ass", Some(Format::Ass)),
+        ("ssa", Some(Format::Ass)),
+        ("sbv", Some(Format::Sbv)),
+        ("sub", Some(Format::MicroDvd(None))),
+        ("txt", Some(Format::Text)),
+        ("json", Some(Format::Json)),
+        ("lrc", Some(Format::Lrc)),
+        ("smi", Some(Format::Sami)),
+        ("sami", Some(Format::Sami)),
+        ("ttml", None),
+        ("dfxp", None),
+        ("", None),
+    ] {
+        assert_eq!(Format::from_extension(ext), format, "{:?}", ext);
+    }
+
+    use std::convert::TryFrom;
+    assert_eq!(Format::try_from(Path::new("a/b.Srt")), Ok(Format::Srt));
+    assert_eq!(Format::try_from(Path::new("a/srt")), Err(()));
+}
+
+/// Display the name of the format, parsed back by `from_str`. The framerate
+/// of MicroDVD is not displayed.
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            Format::WebVTT => "webvtt",
+            Format::Srt => "srt",
+            Format::Ass => "ass",
+            Format::Sbv => "sbv",
+            Format::MicroDvd(_) => "microdvd",
+            Format::Text => "text",
+            Format::Json => "json",
+            Format::Lrc => "lrc",
+            Format::Sami => "sami",
+        })
+    }
+}
+#[test]
+fn test_format_display() {
+    for format in [
+        Format::WebVTT,
+        Format::Srt,
+        Format::Ass,
+        Format::Sbv,
+        Format::MicroDvd(None),
+        Format::Text,
+        Format::Json,
+        Format::Lrc,
+        Format::Sami,
+    ] {
+        assert_eq!(Format::from_str(&format.to_string()), Ok(format));
+    }
+    assert_eq!(Format::MicroDvd(Some(25.0)).to_string(), "microdvd");
+}
+
+/// A reader with the bytes already readed by `detect_format` before the rest.
+pub type Peeked<R> = Chain<Cursor<Vec<u8>>, R>;
+
+/// Detect the format from the first lines of the reader. The readed bytes are
+/// chained before the rest of the reader, so the parser sees the whole stream.
+pub fn detect_format<R: Read>(mut r: R) -> io::Result<(Format, Peeked<R>)> {
+    /// The maximum of readed bytes to detect the format.
+    const MAX: usize = 64 * 1024;
+
+    /// The number of complete and not blank lines.
+    fn complete_lines(buff: &[u8]) -> usize {
+        let mut lines: Vec<&[u8]> = buff.split(|b| *b == b'\n').collect();
+        lines.pop();
+        lines
+            .iter()
+            .filter(|l| !l.iter().all(u8::is_ascii_whitespace))
+            .count()
+    }
+
+    let mut buff: Vec<u8> = Vec::new();
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = r.read(&mut chunk)?;
+        buff.extend_from_slice(&chunk[..n]);
+        if n == 0 || buff.len() >= MAX || complete_lines(&buff) >= 2 {
+            break;
+        }
+    }
+
+    let text = String::from_utf8_lossy(&buff);
+    let mut lines = text
+        .trim_start_matches('\u{FEFF}')
+        .lines()
+        .map(str::trim_end)
+        .filter(|l| !l.is_empty());
+    let format = match (lines.next(), lines.next()) {
+        (Some(l), _) if l.starts_with("WEBVTT") => Format::WebVTT,
+        (Some(l), _) if l.eq_ignore_ascii_case("[script info]") => Format::Ass,
+        (Some(l), _) if l.to_ascii_lowercase().starts_with("<sami") => Format::Sami,
+        (Some(id), Some(time))
+            if id.chars().all(|c| c.is_ascii_digit()) && time.contains(" --> ") =>
+        {
+            Format::Srt
+        }
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Unknown subtitle format (expected a WEBVTT header, a [Script Info] header, a <SAMI> tag or a SRT cue)",
+            ))
+        }
+    };
+
+    Ok((format, Cursor::new(buff).chain(r)))
+}
+#[test]
+fn test_detect_format() {
+    fn detect(s: &str) -> Option<Format> {
+        let (f, mut r) = detect_format(s.as_bytes()).ok()?;
+        let mut all = String::new();
+        r.read_to_string(&mut all).unwrap();
+        assert_eq!(all, s);
+        Some(f)
+    }
+
+    assert!(matches!(
+        detect("\u{FEFF}WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n"),
+        Some(Format::WebVTT)
+    ));
+    assert!(matches!(
+        detect("\n1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\n"),
+        Some(Format::Srt)
+    ));
+    assert!(matches!(
+        detect("[Script Info]\nTitle: Hello\n"),
+        Some(Format::Ass)
+    ));
+    assert!(matches!(detect("<SAMI>\n<HEAD>\n"), Some(Format::Sami)));
+    assert!(detect("Hello\nWorld\n").is_none());
+    assert!(detect("").is_none());
+
+    let long = "1\n00:00:01,000 --> 00:00:02,000\n".to_string() + &"Hello\n".repeat(1000);
+    assert!(matches!(detect(&long), Some(Format::Srt)));
+}
+
+/// Parse the cues of a string. A parser creation error is yielded as the
+/// only item.
+pub fn parse_str(s: &str, format: Format) -> impl Iterator<Item = io::Result<Cue>> + '_ {
+    parse_reader(s.as_bytes(), format)
+}
+
+/// The parsed cues of a boxed parser.
+pub type Parsed<'a> = Box<dyn Iterator<Item = io::Result<Cue>> + 'a>;
+
+/// A parser of any format, see `parser_for`.
+pub trait SubtitleParser: Iterator<Item = io::Result<Cue>> {
+    /// The parsed format.
+    fn format(&self) -> Format;
+    /// The content of the STYLE blocks (WebVTT), empty for the other formats.
+    fn styles(&self) -> &[String] {
+        &[]
+    }
+    /// The REGION definitions (WebVTT), empty for the other formats.
+    fn regions(&self) -> &[Region] {
+        &[]
+    }
+}
+
+/// Create the parser of the format for the reader.
+pub fn parser_for<'a, R: Read + 'a>(
+    r: R,
+    format: Format,
+) -> io::Result<Box<dyn SubtitleParser + 'a>> {
+    fn boxed<'a, P: SubtitleParser + 'a>(
+        p: io::Result<P>,
+    ) -> io::Result<Box<dyn SubtitleParser + 'a>> {
+        Ok(Box::new(p?))
+    }
+
+    match format {
+        Format::WebVTT => boxed(WebVTTParser::new(r)),
+        Format::Srt => boxed(SrtParser::new(r)),
+        Format::Ass => boxed(AssParser::new(r)),
+        Format::Sbv => boxed(SbvParser::new(r)),
+        Format::MicroDvd(_) => boxed(MicroDvdParser::new(r, format.fps()?)),
+        #[cfg(feature = "serde")]
+        Format::Json => boxed(JsonParser::new(r)),
+        #[cfg(not(feature = "serde"))]
+        Format::Json => Err(format.feature_error("serde")),
+        Format::Lrc => boxed(LrcParser::new(r)),
+        Format::Sami => boxed(SamiParser::new(r)),
+        Format::Text => Err(format.input_error()),
+    }
+}
+#[test]
+fn test_parser_for() {
+    let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\n";
+    let vtt = "WEBVTT\n\nSTYLE\n::cue { color: red; }\n\n00:00:01.000 --> 00:00:02.000\nHello\n";
+    for (input, format) in [(srt, Format::Srt), (vtt, Format::WebVTT)] {
+        let p = parser_for(input.as_bytes(), format).unwrap();
+        assert_eq!(p.format().extension(), format.extension());
+        assert_eq!(
+            p.collect::<io::Result<Vec<Cue>>>().unwrap(),
+            vec![Cue::new(
+                None,
+                Duration::new(1, 0),
+                Duration::new(2, 0),
+                vec![String::from("Hello")]
+            )]
+        );
+    }
+    let p = parser_for(vtt.as_bytes(), Format::WebVTT).unwrap();
+    assert_eq!(p.styles(), &[String::from("::cue { color: red; }")]);
+    assert!(parser_for("".as_bytes(), Format::Text).is_err());
+}
+
+/// Parse the cues of the reader with the parser of the format. A parser
+/// creation error is yielded as the only item.
+pub fn parse_reader<'a, R: Read + 'a>(r: R, format: Format) -> Parsed<'a> {
+    match parser_for(r, format) {
+        Ok(p) => Box::new(p),
+        Err(e) => Box::new(std::iter::once(Err(e))),
+    }
+}
+
+/// Parse all the cues of the reader without keeping them, to check that the
+/// input is valid. Return the number of cues, or the first error.
+pub fn validate_parse<R: Read>(r: R, format: Format) -> io::Result<usize> {
+    parser_for(r, format)?.try_fold(0, |nb, c| c.map(|_| nb + 1))
+}
+#[test]
+fn test_validate_parse() {
+    let srt =
+        "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+    assert_eq!(validate_parse(srt.as_bytes(), Format::Srt).unwrap(), 2);
+
+    let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n2\n00:00:03,000 -> 00:00:04,000\nWorld\n";
+    let e = validate_parse(srt.as_bytes(), Format::Srt).unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    match e.into_inner().unwrap().downcast::<ParseError>().map(|e| *e) {
+        Ok(ParseError::BadTimestamp { line, offset, .. }) => {
+            assert_eq!((line, offset), (6, Some(41)))
+        }
+        e => panic!("{:?}", e),
+    }
+}
+
+#[test]
+fn test_new_boxed() {
+    struct Input {
+        name: &'static str,
+        parser: Parsed<'static>,
+    }
+    let srt: SrtParser<Box<dyn Read>> =
+        SrtParser::new_boxed("1\n00:00:01,000 --> 00:00:02,000\nHello\n".as_bytes()).unwrap();
+    let vtt: WebVTTParser<Box<dyn Read>> =
+        WebVTTParser::new_boxed(Cursor::new(b"WEBVTT\n\n00:03.000 --> 00:04.000\nWorld\n"))
+            .unwrap();
+    let inputs = vec![
+        Input {
+            name: "srt",
+            parser: Box::new(srt),
+        },
+        Input {
+            name: "vtt",
+            parser: Box::new(vtt),
+        },
+    ];
+
+    let texts: Vec<(&str, String)> = inputs
+        .into_iter()
+        .flat_map(|Input { name, parser }| parser.map(move |c| (name, c.unwrap().text.join(""))))
+        .collect();
+    assert_eq!(
+        texts,
+        vec![
+            ("srt", String::from("Hello")),
+            ("vtt", String::from("World"))
+        ]
+    );
+}
+
+/// Chain the parsed cues of each input shifted by its offset, like to join
+/// the subtitles of the two parts of a film.
+pub fn concat<'a, I>(inputs: I) -> impl Iterator<Item = io::Result<Cue>> + 'a
+where
+    I: IntoIterator<Item = (Parsed<'a>, Duration)> + 'a,
+{
+    inputs.into_iter().flat_map(|(cues, offset)| {
+        let delta = Delta::Add(offset).applicator();
+        cues.map(move |r| r.map(&delta))
+    })
+}
+#[test]
+fn test_concat() {
+    let a = "1\n00:00:01,000 --> 00:00:02,000\nA1\n\n2\n00:00:03,000 --> 00:00:04,000\nA2\n";
+    let b = "1\n00:00:01,000 --> 00:00:02,000\nB1\n\n2\n00:00:03,000 --> 00:00:04,000\nB2\n";
+    let inputs = vec![
+        (parse_reader(a.as_bytes(), Format::Srt), Duration::ZERO),
+        (
+            parse_reader(b.as_bytes(), Format::Srt),
+            Duration::new(60, 0),
+        ),
+    ];
+
+    let mut out: Vec<u8> = Vec::new();
+    let nb = convert_output_with(concat(inputs), &mut out, Format::Srt, Delta::None, |cues| {
+        Box::new(sort_cues(cues))
+    })
+    .unwrap();
+    assert_eq!(nb, 4);
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1
+00:00:01,000 --> 00:00:02,000
+A1
+
+2
+00:00:03,000 --> 00:00:04,000
+A2
+
+3
+00:01:01,000 --> 00:01:02,000
+B1
+
+4
+00:01:03,000 --> 00:01:04,000
+B2
+
+"
+    );
+}
+
+/// Parse all the cues of a string, return the first error.
+///
+/// ```
+/// use srt2webvtt::{cues_from_str, Format};
+///
+/// let cues = cues_from_str(
+///     "1
+/// 00:00:01,000 --> 00:00:02,000
+/// One
+///
+/// 2
+/// 00:00:03,000 --> 00:00:04,000
+/// Two
+///
+/// 3
+/// 00:00:05,000 --> 00:00:06,000
+/// Three
+/// ",
+///     Format::Srt,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(cues.len(), 3);
+/// assert_eq!(cues[2].text, vec![String::from("Three")]);
+/// ```
+pub fn cues_from_str(s: &str, format: Format) -> io::Result<Vec<Cue>> {
+    parse_str(s, format).collect()
+}
+#[test]
+fn test_cues_from_str() {
+    assert_eq!(
+        cues_from_str("WEBVTT\n\n00:01.000 --> 00:02.000\nHello\n", Format::WebVTT).unwrap(),
+        vec![Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(2, 0),
+            vec![String::from("Hello")]
+        )]
+    );
+    assert!(cues_from_str("1\nHello\n", Format::Srt).is_err());
+    assert!(cues_from_str("Hello\n", Format::WebVTT).is_err());
+}
+
+/// Parse the input and write it again in the same format, to get the
+/// canonical form of a file. Parse then reformat the output give the same
+/// output.
+///
+/// The canonical WebVTT is the `WEBVTT` header line, the REGION blocks with
+/// one setting per line, the STYLE blocks, then each cue after one blank
+/// line: the id (numeric ids are keeped), the time codes as `mm:ss.ttt` or
+/// `hh:mm:ss.ttt` with the settings, and the text lines. The NOTE blocks and
+/// the header text are removed. The
+/// canonical SRT is numbered from 1 with `hh:mm:ss,ttt` time codes. The line
+/// ending is `\n` and the fraction of second is truncated to milliseconds.
+pub fn reformat(input: &str, format: Format) -> io::Result<String> {
+    let mut out: Vec<u8> = Vec::new();
+    match format {
+        Format::WebVTT => {
+            let parser = WebVTTParser::new(input.as_bytes())?.keep_ids(true);
+            let options = WriteOptions {
+                styles: parser.styles().to_vec(),
+                regions: parser.regions().to_vec(),
+                ..WriteOptions::default()
+            };
+            let cues = parser.collect::<io::Result<Vec<Cue>>>()?;
+            webvtt_out_with(cues.into_iter(), &mut out, &options)?;
+        }
+        _ => {
+            let cues = cues_from_str(input, format)?;
+            write_cues(cues.into_iter(), &mut out, format, &WriteOptions::default())?;
+        }
+    }
+    String::from_utf8(out).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+#[test]
+fn test_reformat() {
+    let vtt = "\u{FEFF}WEBVTT - Nitrogen\r
+\r
+STYLE\r
+::cue(b) { color: red; }\r
+\r
+NOTE Hello\r
+\r
+\r
+1\r
+00:00:01.5 --> 00:00:04.000 line:63% position:72%\r
+Never drink liquid nitrogen.\r
+\r
+intro\r
+01:00:05.123456 --> 01:00:09.000\r
+— It will perforate your stomach.";
+    let once = reformat(vtt, Format::WebVTT).unwrap();
+    assert_eq!(
+        once,
+        "WEBVTT
+
+STYLE
+::cue(b) { color: red; }
+
+1
+00:01.500 --> 00:04.000 line:63% position:72%
+Never drink liquid nitrogen.
+
+intro
+01:00:05.123 --> 01:00:09.000
+— It will perforate your stomach.
+
+"
+    );
+    assert_eq!(reformat(&once, Format::WebVTT).unwrap(), once);
+
+    let srt = "5\r\n00:00:01,5 --> 00:00:04,000\r\nHello\r\n\r\n\r\n9\r\n00:00:05,000 --> 00:00:09,000\r\nWorld";
+    let once = reformat(srt, Format::Srt).unwrap();
+    assert_eq!(
+        once,
+        "1\n00:00:01,500 --> 00:00:04,000\nHello\n\n2\n00:00:05,000 --> 00:00:09,000\nWorld\n\n"
+    );
+    assert_eq!(reformat(&once, Format::Srt).unwrap(), once);
+}
+
+/// The options of the writers. Each writer use only the options relevant for
+/// its format.
+#[derive(Clone, Debug, Default)]
+pub struct WriteOptions {
+    /// How to write the inline markup tags (SRT and WebVTT).
+    pub tags: TagStyle,
+    /// Write the lines of a cue on one line (text).
+    pub merge_lines: bool,
+    /// Skip a line identical to the previous one (text).
+    pub collapse_lines: bool,
+    /// Write without whitespace (JSON).
+    pub compact: bool,
+    /// The artist in the header (LRC).
+    pub artist: Option<String>,
+    /// The title in the header (LRC).
+    pub title: Option<String>,
+    /// Omit the blank line after the last cue, keep the blank lines between
+    /// the cues (SRT and WebVTT).
+    pub no_trailing_newline: bool,
+    /// The STYLE blocks written after the header (WebVTT). If empty, a WebVTT
+    /// input keeps its own blocks.
+    pub styles: Vec<String>,
+    /// The REGION blocks written after the header (WebVTT). If empty, a
+    /// WebVTT input keeps its own blocks.
+    pub regions: Vec<Region>,
+    /// Write the cue number as id of the cues without id (WebVTT).
+    pub number_cues: bool,
+    /// Keep or drop the parsed ids of the cues (WebVTT).
+    pub ids: IdPolicy,
+    /// Write a dot instead of a comma before the milliseconds, for the
+    /// players which expect it (SRT).
+    pub srt_dot: bool,
+    /// The line ending of every writed line (all formats).
+    pub line_ending: LineEnding,
+    /// Drop the cues without text, after the transforms (all formats). See
+    /// `drop_empty`.
+    pub drop_empty: bool,
+}
+
+/// How the parsed ids of the cues are handled.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum IdPolicy {
+    /// The numeric ids of the SRT and WebVTT inputs are dropped by the
+    /// parser, the others are writed.
+    #[default]
+    Auto,
+    /// All the parsed ids are keeped and writed, the numeric ones too.
+    Keep,
+    /// No id is writed.
+    Drop,
+}
+
+/// The line ending used by the writers.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum LineEnding {
+    /// `\n`
+    #[default]
+    Lf,
+    /// `\r\n`
+    CrLf,
+}
+impl LineEnding {
+    /// Wrap the writer to use this line ending.
+    pub(crate) fn writer<W: Write>(self, w: W) -> LineEndingWriter<W> {
+        LineEndingWriter {
+            w,
+            crlf: self == LineEnding::CrLf,
+            last_cr: false,
+        }
+    }
+}
+
+/// A writer that replace each `\n` by `\r\n` if crlf. A `\n` already
+/// preceded by a `\r` is keeped.
+pub(crate) struct LineEndingWriter<W: Write> {
+    w: W,
+    crlf: bool,
+    last_cr: bool,
+}
+impl<W: Write> Write for LineEndingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.crlf {
+            return self.w.write(buf);
+        }
+        let mut rest = buf;
+        while let Some(i) = rest.iter().position(|&b| b == b'\n') {
+            self.w.write_all(&rest[..i])?;
+            let cr = if i == 0 {
+                self.last_cr
+            } else {
+                rest[i - 1] == b'\r'
+            };
+            self.w.write_all(if cr { b"\n" } else { b"\r\n" })?;
+            self.last_cr = false;
+            rest = &rest[i + 1..];
+        }
+        self.w.write_all(rest)?;
+        if let Some(&b) = rest.last() {
+            self.last_cr = b == b'\r';
+        }
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.w.flush()
+    }
+}
+#[test]
+fn test_line_ending() {
+    fn convert(format: Format, ending: LineEnding) -> String {
+        let options = WriteOptions {
+            line_ending: ending,
+            ..WriteOptions::default()
+        };
+        let mut out: Vec<u8> = Vec::new();
+        let cues = parse_str(
+            "WEBVTT\r\n\r\nintro\r\n00:01.000 --> 00:02.000\r\nHello\r\nWorld\r\n",
+            Format::WebVTT,
+        );
+        write_cues(cues.map(|c| c.unwrap()), &mut out, format, &options).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    assert_eq!(
+        convert(Format::WebVTT, LineEnding::CrLf),
+        "WEBVTT\r\n\r\nintro\r\n00:01.000 --> 00:02.000\r\nHello\r\nWorld\r\n\r\n"
+    );
+    assert_eq!(
+        convert(Format::WebVTT, LineEnding::Lf),
+        "WEBVTT\n\nintro\n00:01.000 --> 00:02.000\nHello\nWorld\n\n"
+    );
+    for format in [
+        Format::Srt,
+        Format::Ass,
+        Format::Sbv,
+        Format::Text,
+        Format::Lrc,
+    ] {
+        let crlf = convert(format, LineEnding::CrLf);
+        assert_eq!(crlf.matches('\n').count(), crlf.matches("\r\n").count());
+        assert!(!convert(format, LineEnding::Lf).contains('\r'));
+    }
+
+    let mut out: Vec<u8> = Vec::new();
+    let mut w = LineEnding::CrLf.writer(&mut out);
+    w.write_all(b"a\r").unwrap();
+    w.write_all(b"\nb\n").unwrap();
+    assert_eq!(out, b"a\r\nb\r\n");
+}
+
+/// A boxed iterator of cues, for the transforms of `convert_with`.
+pub type Cues<'a> = Box<dyn Iterator<Item = Cue> + 'a>;
 
 /// Convert cues from the input, apply delta duration and save it.
 pub fn convert<R: Read, W: Write>(
@@ -231,20 +1360,177 @@ pub fn convert<R: Read, W: Write>(
     output_format: Format,
     delta: Delta,
 ) -> io::Result<usize> {
-    match input_format {
-        Format::WebVTT => convert_output(
-            WebVTTParser::new(input_reader)?,
-            output_writer,
-            output_format,
-            delta,
-        ),
-        Format::Srt => convert_output(
-            SrtParser::new(input_reader)?,
-            output_writer,
-            output_format,
-            delta,
-        ),
+    convert_with(
+        input_reader,
+        input_format,
+        output_writer,
+        output_format,
+        delta,
+        |cues| cues,
+    )
+}
+
+/// Like `convert`, but the input is decoded from the charset (like
+/// `windows-1252`), or UTF-8 if the charset is unknown.
+#[cfg(feature = "encoding")]
+pub fn convert_with_charset<R: Read, W: Write>(
+    input_reader: R,
+    charset: &str,
+    input_format: Format,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+) -> io::Result<usize> {
+    convert(
+        decode_reader(input_reader, charset),
+        input_format,
+        output_writer,
+        output_format,
+        delta,
+    )
+}
+
+/// Wrap the reader to decode it from the charset into UTF-8. An unknown
+/// charset fall back to UTF-8.
+#[cfg(feature = "encoding")]
+pub fn decode_reader<R: Read>(r: R, charset: &str) -> impl Read {
+    let encoding =
+        encoding_rs::Encoding::for_label(charset.as_bytes()).unwrap_or(encoding_rs::UTF_8);
+    encoding_rs_io::DecodeReaderBytesBuilder::new()
+        .encoding(Some(encoding))
+        .build(r)
+}
+#[cfg(feature = "encoding")]
+#[test]
+fn test_convert_with_charset() {
+    let mut out: Vec<u8> = Vec::new();
+    convert_with_charset(
+        &b"1\n00:00:01,000 --> 00:00:02,000\nCaf\xE9\n"[..],
+        "windows-1252",
+        Format::Srt,
+        &mut out,
+        Format::WebVTT,
+        Delta::None,
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "WEBVTT\n\n00:01.000 --> 00:02.000\nCafé\n\n"
+    );
+}
+
+/// Like `convert`, and apply the transform on the cues after the delta.
+pub fn convert_with<R: Read, W: Write, T>(
+    input_reader: R,
+    input_format: Format,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
+    convert_with_options(
+        input_reader,
+        input_format,
+        output_writer,
+        output_format,
+        delta,
+        transform,
+        &WriteOptions::default(),
+    )
+}
+
+/// Like `convert_with`, with the options of the writer.
+pub fn convert_with_options<R: Read, W: Write, T>(
+    input_reader: R,
+    input_format: Format,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+    options: &WriteOptions,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
+    convert_with_progress(
+        input_reader,
+        input_format,
+        output_writer,
+        output_format,
+        delta,
+        transform,
+        options,
+        |_| {},
+    )
+}
+
+/// Like `convert_with_options`, on_cue is called after each writed cue with
+/// the number of cues writed so far. The total is unknown before the end, so
+/// a caller can only report the progress, like update a GUI every 100 cues.
+#[allow(clippy::too_many_arguments)]
+pub fn convert_with_progress<R: Read, W: Write, T, F: FnMut(usize)>(
+    input_reader: R,
+    input_format: Format,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+    options: &WriteOptions,
+    on_cue: F,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
+    let parser: Box<dyn SubtitleParser> = match (options.ids, input_format) {
+        (IdPolicy::Keep, Format::Srt) => Box::new(SrtParser::new(input_reader)?.keep_ids(true)),
+        (IdPolicy::Keep, Format::WebVTT) => {
+            Box::new(WebVTTParser::new(input_reader)?.keep_ids(true))
+        }
+        _ => parser_for(input_reader, input_format)?,
+    };
+    let mut options = options.clone();
+    if options.styles.is_empty() {
+        options.styles = parser.styles().to_vec();
+    }
+    if options.regions.is_empty() {
+        options.regions = parser.regions().to_vec();
     }
+    write_output(
+        parser,
+        output_writer,
+        output_format,
+        delta,
+        transform,
+        &options,
+        on_cue,
+    )
+}
+#[test]
+fn test_convert_with_progress() {
+    let srt = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n\
+        2\n00:00:03,000 --> 00:00:04,000\n\n\
+        3\n00:00:05,000 --> 00:00:06,000\nWorld\n\n\
+        4\n00:00:07,000 --> 00:00:08,000\n!\n";
+    let mut counts = Vec::new();
+    let nb = convert_with_progress(
+        srt.as_bytes(),
+        Format::Srt,
+        io::sink(),
+        Format::WebVTT,
+        Delta::None,
+        |cues| cues,
+        &WriteOptions {
+            drop_empty: true,
+            ..WriteOptions::default()
+        },
+        |nb| counts.push(nb),
+    )
+    .unwrap();
+    assert_eq!(nb, 3);
+    assert_eq!(counts, vec![1, 2, 3]);
 }
 #[test]
 fn test_convert() {
@@ -286,68 +1572,455 @@ identifier
     );
 }
 
-/// Apply the delta time to all input cues and save them into the output_writer.
+#[test]
+fn test_convert_text() {
+    let mut out: Vec<u8> = Vec::new();
+    convert(
+        "1\n00:00:01,000 --> 00:00:02,000\nHello\nWorld\n".as_bytes(),
+        Format::Srt,
+        &mut out,
+        Format::Text,
+        Delta::None,
+    )
+    .unwrap();
+    assert_eq!(std::str::from_utf8(&out).unwrap(), "Hello\nWorld\n");
+
+    let e = convert(
+        "Hello".as_bytes(),
+        Format::Text,
+        io::sink(),
+        Format::Srt,
+        Delta::None,
+    );
+    assert_eq!(e.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+}
+#[cfg(feature = "serde")]
+#[test]
+fn test_convert_json() {
+    let mut json: Vec<u8> = Vec::new();
+    convert(
+        "WEBVTT\n\nintro\n00:01.000 --> 00:04.500\nHello\nWorld\n".as_bytes(),
+        Format::WebVTT,
+        &mut json,
+        Format::Json,
+        Delta::None,
+    )
+    .unwrap();
+
+    let mut out: Vec<u8> = Vec::new();
+    convert(&json[..], Format::Json, &mut out, Format::Srt, Delta::None).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1\n00:00:01,000 --> 00:00:04,500\nHello\nWorld\n\n"
+    );
+}
+#[test]
+fn test_convert_drop_empty() {
+    let input = "1
+00:00:01,000 --> 00:00:02,000
+
+2
+00:00:03,000 --> 00:00:04,000
+Hello
+";
+    // The empty cues are keeped by default.
+    let mut out: Vec<u8> = Vec::new();
+    let nb = convert(
+        input.as_bytes(),
+        Format::Srt,
+        &mut out,
+        Format::Srt,
+        Delta::None,
+    )
+    .unwrap();
+    assert_eq!(nb, 2);
+
+    let mut out: Vec<u8> = Vec::new();
+    let options = WriteOptions {
+        drop_empty: true,
+        ..WriteOptions::default()
+    };
+    let nb = convert_with_options(
+        input.as_bytes(),
+        Format::Srt,
+        &mut out,
+        Format::Srt,
+        Delta::None,
+        |cues| cues,
+        &options,
+    )
+    .unwrap();
+
+    assert_eq!(nb, 1);
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1
+00:00:03,000 --> 00:00:04,000
+Hello
+
+"
+    );
+}
+
+#[test]
+fn test_convert_with() {
+    let mut out: Vec<u8> = Vec::new();
+    convert_with(
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n<i>Hello</i>\n\n00:03.000 --> 00:04.000\n<b></b>\n"
+            .as_bytes(),
+        Format::WebVTT,
+        &mut out,
+        Format::Srt,
+        Delta::None,
+        |cues| Box::new(drop_empty(strip_tags(cues))),
+    )
+    .unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n"
+    );
+}
+
+/// Apply the delta time to all input cues and save them into the
+/// output_writer. Return the number of writed cues.
 pub fn convert_output<I: Iterator<Item = io::Result<Cue>>, W: Write>(
-    mut input: I,
+    input: I,
     output_writer: W,
     output_format: Format,
     delta: Delta,
 ) -> io::Result<usize> {
+    convert_output_with(input, output_writer, output_format, delta, |cues| cues)
+}
+
+/// Like `convert_output`, and apply the transform on the cues after the delta.
+pub fn convert_output_with<I: Iterator<Item = io::Result<Cue>>, W: Write, T>(
+    input: I,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
+    convert_output_with_options(
+        input,
+        output_writer,
+        output_format,
+        delta,
+        transform,
+        &WriteOptions::default(),
+    )
+}
+
+/// Like `convert_output_with`, with the options of the writer.
+pub fn convert_output_with_options<I: Iterator<Item = io::Result<Cue>>, W: Write, T>(
+    input: I,
+    output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+    options: &WriteOptions,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
+    write_output(
+        input,
+        output_writer,
+        output_format,
+        delta,
+        transform,
+        options,
+        |_| {},
+    )
+}
+
+/// Like `convert_output_with_options`, and call on_cue after each writed cue,
+/// see `convert_with_progress`.
+fn write_output<I: Iterator<Item = io::Result<Cue>>, W: Write, T, F: FnMut(usize)>(
+    input: I,
+    mut output_writer: W,
+    output_format: Format,
+    delta: Delta,
+    transform: T,
+    options: &WriteOptions,
+    mut on_cue: F,
+) -> io::Result<usize>
+where
+    T: for<'a> FnOnce(Cues<'a>) -> Cues<'a>,
+{
     let mut error: Option<io::Error> = None;
 
-    let cues = (&mut input)
-        .filter_map(|r| {
-            if error.is_some() {
+    // Stop to read the input on the first error, the writer ends with the
+    // cues already readed.
+    let cues = input
+        .map_while(|r| match r {
+            Ok(c) => Some(c),
+            Err(e) => {
+                error = Some(e);
                 None
-            } else {
-                match r {
-                    Ok(c) => Some(c),
-                    Err(e) => {
-                        error = Some(e);
-                        None
-                    }
-                }
             }
         })
         .map(delta.applicator());
+    let mut cues = transform(Box::new(cues));
+    if options.drop_empty {
+        cues = Box::new(drop_empty(cues));
+    }
 
-    let nb = match output_format {
-        Format::WebVTT => webvtt_out,
-        Format::Srt => srt_out,
-    }(cues, output_writer)?;
+    // A cue is writed when the writer asks the next one.
+    let mut readed = 0;
+    let mut reported = 0;
+    let mut report = |readed: usize| {
+        if reported < readed {
+            reported = readed;
+            on_cue(readed);
+        }
+    };
+    let progress = std::iter::from_fn(|| {
+        report(readed);
+        let c = cues.next()?;
+        readed += 1;
+        Some(c)
+    });
+    let nb = write_cues(progress, &mut output_writer, output_format, options)?;
+    report(nb);
+    drop(cues); // Release the borrow of error.
 
     match error {
-        Some(e) => Err(e),
+        Some(e) => {
+            output_writer.flush()?;
+            Err(e)
+        }
         None => Ok(nb),
     }
 }
+#[test]
+fn test_convert_output_stop() {
+    let cue = Cue::new(
+        None,
+        Duration::new(1, 0),
+        Duration::new(2, 0),
+        vec![String::from("Hello")],
+    );
+    let input = vec![Ok(cue.clone()), Err(io::Error::other("broken"))]
+        .into_iter()
+        .chain(std::iter::repeat_with(|| -> io::Result<Cue> {
+            panic!("The input is readed after the error")
+        }));
+
+    let mut out: Vec<u8> = Vec::new();
+    let e = convert_output(input, &mut out, Format::Srt, Delta::None).unwrap_err();
+    assert_eq!(e.to_string(), "broken");
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n"
+    );
+}
+
+/// Write the cues with the writer of the format. Return the number of writed
+/// cues.
+pub fn write_cues<I: Iterator<Item = Cue>, W: Write>(
+    cues: I,
+    mut w: W,
+    format: Format,
+    options: &WriteOptions,
+) -> io::Result<usize> {
+    writer_for(format)?(Box::new(cues), &mut w, options)
+}
+
+/// A writer of the cues in one format, see `writer_for`. Return the number of
+/// writed cues.
+pub type SubtitleWriter =
+    Box<dyn for<'a> Fn(Cues<'a>, &mut dyn Write, &WriteOptions) -> io::Result<usize>>;
+
+/// Get the writer of the format. An input only format, or a MicroDVD format
+/// without framerate, is an error.
+pub fn writer_for(format: Format) -> io::Result<SubtitleWriter> {
+    Ok(match format {
+        Format::WebVTT => Box::new(|cues, w, options| webvtt_out_with(cues, w, options)),
+        Format::Srt => Box::new(|cues, w, options| srt_out_with(cues, w, options)),
+        Format::Ass => Box::new(|cues, w, options| ass_out(cues, options.line_ending.writer(w))),
+        Format::Sbv => Box::new(|cues, w, options| sbv_out(cues, options.line_ending.writer(w))),
+        Format::MicroDvd(_) => {
+            let fps = format.fps()?;
+            Box::new(move |cues, w, options| microdvd_out(cues, options.line_ending.writer(w), fps))
+        }
+        Format::Text => Box::new(|cues, w, options| text_out_with(cues, w, options)),
+        Format::Lrc => Box::new(|cues, w, options| lrc_out_with(cues, w, options)),
+        Format::Sami => return Err(format.output_error()),
+        #[cfg(feature = "serde")]
+        Format::Json => Box::new(|cues, w, options| json_out_with(cues, w, options)),
+        #[cfg(not(feature = "serde"))]
+        Format::Json => return Err(format.feature_error("serde")),
+    })
+}
+#[test]
+fn test_writer_for() {
+    let cue = Cue::new(
+        None,
+        Duration::new(1, 0),
+        Duration::new(2, 0),
+        vec![String::from("Hello")],
+    );
+    let formats = [
+        Format::WebVTT,
+        Format::Srt,
+        Format::Ass,
+        Format::Sbv,
+        Format::MicroDvd(Some(25.0)),
+        Format::Text,
+        Format::Lrc,
+        #[cfg(feature = "serde")]
+        Format::Json,
+    ];
+    for format in formats {
+        let writer = writer_for(format).unwrap();
+        let mut out: Vec<u8> = Vec::new();
+        let nb = writer(
+            Box::new(std::iter::once(cue.clone())),
+            &mut out,
+            &WriteOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(nb, 1, "{:?}", format);
+        assert!(std::str::from_utf8(&out).unwrap().contains("Hello"));
+    }
+    assert!(writer_for(Format::Sami).is_err());
+    assert!(writer_for(Format::MicroDvd(None)).is_err());
+}
+
+/// Parse the nanoseconds from the fraction part of a time code, with one
+/// digit or more: `5` is 500 milliseconds and `123456` is 123456 microseconds.
+/// The digits after the nanoseconds are truncated.
+fn parse_nanos(frac: &str) -> Option<u32> {
+    if frac.is_empty() || !frac.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    format!("{:0<9}", &frac[..frac.len().min(9)]).parse().ok()
+}
+#[test]
+fn test_parse_nanos() {
+    assert_eq!(parse_nanos("5"), Some(500_000_000));
+    assert_eq!(parse_nanos("054"), Some(54_000_000));
+    assert_eq!(parse_nanos("123456"), Some(123_456_000));
+    assert_eq!(parse_nanos("1234567891"), Some(123_456_789));
+    assert_eq!(parse_nanos(""), None);
+    assert_eq!(parse_nanos("5a"), None);
+}
 
-/// A line by line reader that count readed lines.
+/// A line by line reader that count readed lines and bytes. The line ending
+/// can be `\n` or `\r\n`.
 struct LineNb<R: Read> {
-    lines: Lines<BufReader<R>>,
+    reader: BufReader<R>,
     nb: usize,
+    /// The byte offset of the current line and of the previous line.
+    offset: u64,
+    prev_offset: u64,
+    /// The number of readed bytes.
+    readed: u64,
+    /// The text of the current line and of the previous line.
+    raw: String,
+    prev_raw: String,
 }
 impl<R: Read> LineNb<R> {
     pub fn new(r: R) -> Self {
         Self {
-            lines: BufReader::new(r).lines(),
+            reader: BufReader::new(r),
             nb: 0,
+            offset: 0,
+            prev_offset: 0,
+            readed: 0,
+            raw: String::new(),
+            prev_raw: String::new(),
         }
     }
     /// Return the current line number.
     pub fn current(&self) -> usize {
         self.nb
     }
+    /// Return the byte offset of the current line begin.
+    pub fn byte_offset(&self) -> u64 {
+        self.offset
+    }
+    /// Set the byte offset and the text of the current or the previous line
+    /// into the ParseError of e.
+    pub fn locate(&self, mut e: io::Error) -> io::Error {
+        if let Some(pe) = e.get_mut().and_then(|e| e.downcast_mut::<ParseError>()) {
+            pe.locate(|line| match line {
+                l if l == self.nb => Some((self.byte_offset(), self.raw.clone())),
+                l if l + 1 == self.nb => Some((self.prev_offset, self.prev_raw.clone())),
+                _ => None,
+            });
+        }
+        e
+    }
 }
 impl<R: Read> Iterator for LineNb<R> {
     type Item = io::Result<String>;
     fn next(&mut self) -> Option<Self::Item> {
-        match self.lines.next() {
-            Some(Ok(l)) => {
+        let mut l = String::new();
+        match self.reader.read_line(&mut l) {
+            Ok(0) => None,
+            Ok(n) => {
                 self.nb += 1;
+                self.prev_offset = self.offset;
+                self.offset = self.readed;
+                self.readed += n as u64;
+                if l.ends_with('\n') {
+                    l.pop();
+                }
+                if l.ends_with('\r') {
+                    l.pop();
+                }
+                std::mem::swap(&mut self.raw, &mut self.prev_raw);
+                self.raw.clear();
+                self.raw.push_str(&l);
                 Some(Ok(l))
             }
-            x => x,
+            Err(e) => Some(Err(e)),
         }
     }
 }
+#[test]
+fn line_nb_offset() {
+    let mut lines = LineNb::new("héllo\r\n€\nend".as_bytes());
+    assert_eq!(lines.next().unwrap().unwrap(), "héllo");
+    assert_eq!((lines.current(), lines.byte_offset()), (1, 0));
+    assert_eq!(lines.next().unwrap().unwrap(), "€");
+    assert_eq!((lines.current(), lines.byte_offset()), (2, 8));
+    assert_eq!(lines.next().unwrap().unwrap(), "end");
+    assert_eq!((lines.current(), lines.byte_offset()), (3, 12));
+    assert!(lines.next().is_none());
+
+    let e = lines.locate(ParseError::unexpected::<()>("€", 2).unwrap_err());
+    assert_eq!(e.to_string(), "Unexpected line \"€\" (line 2, byte 8)");
+}
+#[test]
+fn crlf() {
+    let srt = "1\r\n00:00:01,000 --> 00:00:02,000\r\nHello\r\n\r\n2\r\n00:00:03,000 --> 00:00:04,000\r\nWorld\r";
+    let vtt = srt.replace("1\r\n", "WEBVTT\r\n\r\n").replace(',', ".");
+
+    for cues in [
+        cues_from_str(srt, Format::Srt).unwrap(),
+        cues_from_str(&vtt, Format::WebVTT).unwrap(),
+    ] {
+        assert_eq!(
+            cues,
+            vec![
+                Cue::new(
+                    None,
+                    Duration::new(1, 0),
+                    Duration::new(2, 0),
+                    vec![String::from("Hello")]
+                ),
+                Cue::new(
+                    None,
+                    Duration::new(3, 0),
+                    Duration::new(4, 0),
+                    vec![String::from("World")]
+                ),
+            ]
+        );
+    }
+}
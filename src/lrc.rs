@@ -0,0 +1,260 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{parse_nanos, Cue, Format, LineNb, ParseError, SubtitleParser, WriteOptions};
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+/// A parser of a LRC lyrics stream. A cue ends at the begin of the next line,
+/// so all the stream is readed at the first cue. The metadata lines like
+/// `[ti:Title]` are skipped.
+pub struct LrcParser<R: Read> {
+    lines: LineNb<BufReader<R>>,
+    cues: Option<std::vec::IntoIter<Cue>>,
+    last_duration: Duration,
+}
+impl<R: Read> LrcParser<R> {
+    pub fn new(r: R) -> io::Result<Self> {
+        use std::io::BufRead;
+
+        let mut input = BufReader::new(r);
+
+        let first = input.fill_buf()?;
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
+            input.consume(3);
+        }
+
+        Ok(Self {
+            lines: LineNb::new(input),
+            cues: None,
+            last_duration: Duration::from_secs(5),
+        })
+    }
+    /// The duration of the last cue, which has no next line. Default to five
+    /// seconds.
+    pub fn last_duration(mut self, d: Duration) -> Self {
+        self.last_duration = d;
+        self
+    }
+    /// Read all the lines, and return the cues sorted by time.
+    fn read_all(&mut self) -> io::Result<Vec<Cue>> {
+        let mut entries: Vec<(Duration, String)> = Vec::new();
+        while let Some(l) = self.lines.next() {
+            let l = l?;
+            let line = self.lines.current();
+            let mut rest = l.trim();
+            if rest.is_empty() {
+                continue;
+            }
+
+            let mut times = Vec::new();
+            while rest.starts_with('[') {
+                let end = match rest.find(']') {
+                    Some(end) => end,
+                    None => return ParseError::unexpected(&l, line),
+                };
+                let tag = &rest[1..end];
+                if !tag.starts_with(|c: char| c.is_ascii_digit()) {
+                    break;
+                }
+                times.push(parse_duration(tag, line)?);
+                rest = &rest[end + 1..];
+            }
+
+            if times.is_empty() && !rest.starts_with('[') {
+                return ParseError::unexpected(&l, line);
+            }
+            for t in times {
+                entries.push((t, rest.to_string()));
+            }
+        }
+
+        entries.sort_by_key(|e| e.0);
+        let ends: Vec<Duration> = entries.iter().skip(1).map(|e| e.0).collect();
+        Ok(entries
+            .into_iter()
+            .enumerate()
+            .map(|(i, (begin, text))| {
+                let end = ends.get(i).copied().unwrap_or(begin + self.last_duration);
+                Cue::new(None, begin, end, vec![text])
+            })
+            .collect())
+    }
+}
+impl<R: Read> SubtitleParser for LrcParser<R> {
+    fn format(&self) -> Format {
+        Format::Lrc
+    }
+}
+impl<R: Read> Iterator for LrcParser<R> {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        if self.cues.is_none() {
+            match self.read_all() {
+                Ok(cues) => self.cues = Some(cues.into_iter()),
+                Err(e) => {
+                    self.cues = Some(Vec::new().into_iter());
+                    return Some(Err(self.lines.locate(e)));
+                }
+            }
+        }
+        self.cues.as_mut()?.next().map(Ok)
+    }
+}
+#[test]
+fn lrcparser() {
+    let p = LrcParser::new(
+        "[ti:Nitrogen]
+[ar:Bob]
+[00:01.00]Never drink liquid nitrogen.
+[00:04.5]It will perforate your stomach.
+
+[00:09.250][00:20.00]You could die.
+"
+        .as_bytes(),
+    )
+    .unwrap();
+
+    let cue = |begin: u64, end: u64, text: &str| {
+        Cue::new(
+            None,
+            Duration::from_millis(begin),
+            Duration::from_millis(end),
+            vec![String::from(text)],
+        )
+    };
+    assert_eq!(
+        p.collect::<io::Result<Vec<Cue>>>().unwrap(),
+        vec![
+            cue(1000, 4500, "Never drink liquid nitrogen."),
+            cue(4500, 9250, "It will perforate your stomach."),
+            cue(9250, 20000, "You could die."),
+            cue(20000, 25000, "You could die."),
+        ]
+    );
+
+    let mut p = LrcParser::new("[00:01.00]Hello\nWorld\n".as_bytes()).unwrap();
+    assert_eq!(
+        p.next().unwrap().unwrap_err().kind(),
+        io::ErrorKind::InvalidData
+    );
+    assert!(p.next().is_none());
+}
+
+/// Parse a `mm:ss.xx` time tag content.
+fn parse_duration(s: &str, line: usize) -> io::Result<Duration> {
+    let (min, sec) = match s.find(':') {
+        Some(sep) => (&s[..sep], &s[sep + 1..]),
+        None => return ParseError::timestamp("Invalid duration syntax", s, line),
+    };
+    let (sec, nanos) = match sec.find('.') {
+        Some(sep) => match parse_nanos(&sec[sep + 1..]) {
+            Some(nanos) => (&sec[..sep], nanos),
+            None => return ParseError::timestamp("Invalid fraction of second", s, line),
+        },
+        None => (sec, 0),
+    };
+    let min: u64 = min.parse().or_else(|e| ParseError::timestamp(e, s, line))?;
+    let sec: u64 = sec.parse().or_else(|e| ParseError::timestamp(e, s, line))?;
+    Ok(Duration::new(min * 60 + sec, nanos))
+}
+
+/// Write all Cues from the input Iterator into the write W. Use LRC lyrics
+/// format: each text line is writed with the begin of the cue, the end is
+/// ignored. Return the number fo writed cue.
+pub fn out<I, W>(cues: I, w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    out_with(cues, w, &WriteOptions::default())
+}
+
+/// Like `out` with the writing options: `artist` and `title` are writed in
+/// the `[ar:]` and `[ti:]` header.
+pub fn out_with<I, W>(cues: I, w: W, options: &WriteOptions) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut w = options.line_ending.writer(w);
+    if let Some(artist) = &options.artist {
+        writeln!(w, "[ar:{}]", artist)?;
+    }
+    if let Some(title) = &options.title {
+        writeln!(w, "[ti:{}]", title)?;
+    }
+
+    let mut nb = 0;
+    for c in cues {
+        for l in c.text {
+            write_duration(&mut w, &c.begin)?;
+            writeln!(w, "{}", l)?;
+        }
+        nb += 1;
+    }
+
+    Ok(nb)
+}
+#[test]
+fn test_out() {
+    let cues = vec![
+        Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        ),
+        Cue::new(
+            None,
+            Duration::new(65, 500_000_000),
+            Duration::new(69, 0),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die."),
+            ],
+        ),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out(cues.clone().into_iter(), &mut output).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "[00:01.00]Never drink liquid nitrogen.
+[01:05.50]— It will perforate your stomach.
+[01:05.50]— You could die.
+"
+    );
+
+    let options = WriteOptions {
+        artist: Some(String::from("Bob")),
+        title: Some(String::from("Nitrogen")),
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    out_with(cues.into_iter().take(1), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "[ar:Bob]\n[ti:Nitrogen]\n[00:01.00]Never drink liquid nitrogen.\n"
+    );
+}
+
+/// Write one `[mm:ss.xx]` time tag, the minutes are not limited to 99.
+fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), io::Error> {
+    let sec = d.as_secs();
+    write!(
+        w,
+        "[{:02}:{:02}.{:02}]",
+        sec / 60,
+        sec % 60,
+        d.subsec_millis() / 10
+    )
+}
+#[test]
+fn test_write_duration() {
+    let d = Duration::new(2 * 3600 + 3 * 60 + 5, 849_999_999);
+    let mut out: Vec<u8> = Vec::new();
+    write_duration(&mut out, &d).unwrap();
+    assert_eq!(std::str::from_utf8(&out).unwrap(), "[123:05.84]");
+}
@@ -0,0 +1,212 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, Format, LineNb, ParseError, SubtitleParser};
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+/// A parser of a MicroDVD stream (`{begin}{end}text|text`), with the frame
+/// numbers converted into time with the framerate.
+pub struct MicroDvdParser<R: Read> {
+    lines: LineNb<BufReader<R>>,
+    end: bool,
+    fps: f64,
+}
+impl<R: Read> MicroDvdParser<R> {
+    pub fn new(r: R, fps: f64) -> io::Result<Self> {
+        use std::io::BufRead;
+
+        if !fps.is_finite() || fps <= 0.0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("Invalid framerate {} for MicroDVD", fps),
+            ));
+        }
+
+        let mut input = BufReader::new(r);
+
+        let first = input.fill_buf()?;
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
+            input.consume(3);
+        }
+
+        Ok(Self {
+            lines: LineNb::new(input),
+            end: false,
+            fps,
+        })
+    }
+    /// Parse one line into a cue.
+    fn parse_line(&self, l: &str) -> io::Result<Cue> {
+        let line = self.lines.current();
+        let (begin, rest) = parse_frame(l, line)?;
+        let (end, text) = parse_frame(rest, line)?;
+        Ok(Cue::new(
+            None,
+            frame_to_duration(begin, self.fps),
+            frame_to_duration(end, self.fps),
+            text.split('|').map(String::from).collect(),
+        ))
+    }
+}
+impl<R: Read> SubtitleParser for MicroDvdParser<R> {
+    fn format(&self) -> Format {
+        Format::MicroDvd(Some(self.fps))
+    }
+}
+impl<R: Read> Iterator for MicroDvdParser<R> {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        if self.end {
+            return None;
+        }
+
+        // Skip the blank lines between the cues.
+        let cue = loop {
+            match self.lines.next() {
+                None => {
+                    self.end = true;
+                    return None;
+                }
+                Some(Ok(l)) if l.trim().is_empty() => continue,
+                Some(Ok(l)) => break self.parse_line(&l),
+                Some(Err(e)) => break Err(e),
+            }
+        };
+        if cue.is_err() {
+            self.end = true;
+        }
+        Some(cue.map_err(|e| self.lines.locate(e)))
+    }
+}
+#[test]
+fn microdvdparser() {
+    let mut p = MicroDvdParser::new(
+        "{25}{100}Never drink liquid nitrogen.
+
+{125}{237}— It will perforate your stomach.|— You could die.
+"
+        .as_bytes(),
+        25.0,
+    )
+    .unwrap();
+
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        )
+    );
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(5, 0),
+            Duration::new(9, 480_000_000),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die."),
+            ],
+        )
+    );
+    assert!(p.next().is_none());
+
+    let e = MicroDvdParser::new("{25}Hello".as_bytes(), 25.0)
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap_err();
+    assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    assert!(MicroDvdParser::new("".as_bytes(), 0.0).is_err());
+
+    // Many blank lines do not overflow the stack.
+    let input = format!("{}{{25}}{{50}}Hello\n", "\n".repeat(1_000_000));
+    let mut p = MicroDvdParser::new(input.as_bytes(), 25.0).unwrap();
+    assert_eq!(p.next().unwrap().unwrap().text, vec![String::from("Hello")]);
+    assert!(p.next().is_none());
+}
+
+/// Parse a `{frame}` at the begin of s, return the frame and the rest.
+fn parse_frame(s: &str, line: usize) -> io::Result<(u64, &str)> {
+    let end = match (s.starts_with('{'), s.find('}')) {
+        (true, Some(end)) => end,
+        _ => return ParseError::unexpected(s, line),
+    };
+    match s[1..end].trim().parse() {
+        Ok(frame) => Ok((frame, &s[end + 1..])),
+        Err(e) => ParseError::timestamp(e, &s[..=end], line),
+    }
+}
+
+/// Convert a frame number into the time.
+fn frame_to_duration(frame: u64, fps: f64) -> Duration {
+    Duration::from_secs_f64(frame as f64 / fps)
+}
+#[test]
+fn test_frame_to_duration() {
+    assert_eq!(frame_to_duration(25, 25.0), Duration::new(1, 0));
+    assert_eq!(frame_to_duration(0, 23.976), Duration::ZERO);
+}
+
+/// Write all Cues from the input Iterator into the write W. Use MicroDVD
+/// subtitle format, the times are rounded to the nearest frame, so a round
+/// trip loses the precision under one frame. The cue id are ignored. Return
+/// the number fo writed cue.
+pub fn out<I, W>(cues: I, mut w: W, fps: f64) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut nb = 0;
+
+    for c in cues {
+        writeln!(
+            w,
+            "{{{}}}{{{}}}{}",
+            duration_to_frame(c.begin, fps),
+            duration_to_frame(c.end, fps),
+            c.text.join("|")
+        )?;
+        nb += 1;
+    }
+
+    Ok(nb)
+}
+#[test]
+fn test_out() {
+    let cues = vec![
+        Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        ),
+        Cue::new(
+            None,
+            Duration::new(5, 0),
+            Duration::new(9, 510_000_000),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die."),
+            ],
+        ),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out(cues.into_iter(), &mut output, 30.0).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "{30}{120}Never drink liquid nitrogen.
+{150}{285}— It will perforate your stomach.|— You could die.
+"
+    );
+}
+
+/// Convert a time into the nearest frame number.
+fn duration_to_frame(d: Duration, fps: f64) -> u64 {
+    (d.as_secs_f64() * fps).round() as u64
+}
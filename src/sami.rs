@@ -0,0 +1,291 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, Format, LineNb, ParseError, SubtitleParser};
+use std::io::{self, BufReader, Read};
+use std::time::Duration;
+
+/// A parser of a SAMI stream (`.smi`). Each `<SYNC Start=ms>` begins a cue
+/// which ends at the next `<SYNC>`, a `<SYNC>` without text only ends the
+/// previous cue. The `<P>` text is HTML decoded and `<br>` splits the lines.
+/// All the stream is readed at the first cue.
+pub struct SamiParser<R: Read> {
+    lines: LineNb<BufReader<R>>,
+    cues: Option<std::vec::IntoIter<Cue>>,
+    last_duration: Duration,
+    lang: Option<String>,
+}
+impl<R: Read> SamiParser<R> {
+    pub fn new(r: R) -> io::Result<Self> {
+        use std::io::BufRead;
+
+        let mut input = BufReader::new(r);
+
+        let first = input.fill_buf()?;
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
+            input.consume(3);
+        }
+
+        Ok(Self {
+            lines: LineNb::new(input),
+            cues: None,
+            last_duration: Duration::from_secs(5),
+            lang: None,
+        })
+    }
+    /// The duration of the last cue, which has no next `<SYNC>`. Default to
+    /// five seconds.
+    pub fn last_duration(mut self, d: Duration) -> Self {
+        self.last_duration = d;
+        self
+    }
+    /// Keep only the `<P>` of this language class, like `ENCC`. Default to
+    /// all the classes.
+    pub fn lang(mut self, class: Option<String>) -> Self {
+        self.lang = class;
+        self
+    }
+    /// Read all the stream, and return the cues sorted by time.
+    fn read_all(&mut self) -> io::Result<Vec<Cue>> {
+        let mut src = String::new();
+        let mut line_begins = Vec::new();
+        for l in self.lines.by_ref() {
+            line_begins.push(src.len());
+            src.push_str(&l?);
+            src.push('\n');
+        }
+        let line_of = |pos: usize| line_begins.partition_point(|&b| b <= pos);
+
+        let lower = src.to_ascii_lowercase();
+        let body_end = lower.find("</body").unwrap_or(src.len());
+        let syncs: Vec<usize> = lower[..body_end]
+            .match_indices("<sync")
+            .map(|(i, _)| i)
+            .collect();
+
+        let mut entries: Vec<(Duration, Vec<String>)> = Vec::new();
+        for (i, &pos) in syncs.iter().enumerate() {
+            let tag_end = match src[pos..].find('>') {
+                Some(end) => pos + end,
+                None => return ParseError::unexpected(&src[pos..], line_of(pos)),
+            };
+            let tag = &src[pos..=tag_end];
+            let begin = match attribute(tag, "start").map(str::parse::<u64>) {
+                Some(Ok(ms)) => Duration::from_millis(ms),
+                Some(Err(e)) => return ParseError::timestamp(e, tag, line_of(pos)),
+                None => return ParseError::timestamp("Need a Start", tag, line_of(pos)),
+            };
+            let end = syncs.get(i + 1).copied().unwrap_or(body_end);
+            let text = block_text(&src[tag_end + 1..end], self.lang.as_deref());
+            entries.push((begin, text));
+        }
+
+        entries.sort_by_key(|e| e.0);
+        let mut cues = Vec::new();
+        for (i, (begin, text)) in entries.iter().enumerate() {
+            if text.is_empty() {
+                continue;
+            }
+            let end = match entries.get(i + 1) {
+                Some((next, _)) => *next,
+                None => *begin + self.last_duration,
+            };
+            cues.push(Cue::new(None, *begin, end, text.clone()));
+        }
+        Ok(cues)
+    }
+}
+impl<R: Read> SubtitleParser for SamiParser<R> {
+    fn format(&self) -> Format {
+        Format::Sami
+    }
+}
+impl<R: Read> Iterator for SamiParser<R> {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        if self.cues.is_none() {
+            match self.read_all() {
+                Ok(cues) => self.cues = Some(cues.into_iter()),
+                Err(e) => {
+                    self.cues = Some(Vec::new().into_iter());
+                    return Some(Err(self.lines.locate(e)));
+                }
+            }
+        }
+        self.cues.as_mut()?.next().map(Ok)
+    }
+}
+#[test]
+fn samiparser() {
+    let p = SamiParser::new(
+        "<SAMI>
+<HEAD><TITLE>Nitrogen</TITLE></HEAD>
+<BODY>
+<SYNC Start=1000><P Class=ENCC>Never drink<br>liquid &amp; nitrogen.
+<SYNC Start=4000><P Class=ENCC>&nbsp;
+<SYNC Start=5000><P Class=ENCC>It will perforate
+your stomach.</P>
+<P Class=FRCC>Il va perforer ton estomac.</P>
+<SYNC Start=9500><P Class=ENCC>&nbsp;
+</BODY>
+</SAMI>
+"
+        .as_bytes(),
+    )
+    .unwrap()
+    .lang(Some(String::from("encc")));
+
+    let cue = |begin: u64, end: u64, text: &[&str]| {
+        Cue::new(
+            None,
+            Duration::from_millis(begin),
+            Duration::from_millis(end),
+            text.iter().map(|t| t.to_string()).collect(),
+        )
+    };
+    assert_eq!(
+        p.map(|c| c.unwrap()).collect::<Vec<Cue>>(),
+        vec![
+            cue(1000, 4000, &["Never drink", "liquid & nitrogen."]),
+            cue(5000, 9500, &["It will perforate your stomach."]),
+        ]
+    );
+
+    let mut p = SamiParser::new("<SYNC Start=x><P>Hello".as_bytes()).unwrap();
+    assert!(p.next().unwrap().is_err());
+}
+
+/// Get the value of the attribute name (in lower case) of the tag.
+fn attribute<'a>(tag: &'a str, name: &str) -> Option<&'a str> {
+    let lower = tag.to_ascii_lowercase();
+    let mut from = 0;
+    while let Some(i) = lower[from..].find(name) {
+        let i = from + i;
+        from = i + name.len();
+        if !lower[..i].ends_with(char::is_whitespace) {
+            continue;
+        }
+        let rest = tag[from..].trim_start();
+        let value = match rest.strip_prefix('=') {
+            Some(value) => value.trim_start(),
+            None => continue,
+        };
+        return Some(match value.strip_prefix(['"', '\'']) {
+            Some(quoted) => quoted.split(['"', '\'']).next().unwrap_or(""),
+            None => value
+                .split(|c: char| c.is_whitespace() || c == '>')
+                .next()
+                .unwrap_or(""),
+        });
+    }
+    None
+}
+#[test]
+fn test_attribute() {
+    assert_eq!(attribute("<SYNC Start=1000>", "start"), Some("1000"));
+    assert_eq!(attribute("<P class = \"ENCC\">", "class"), Some("ENCC"));
+    assert_eq!(attribute("<P>", "class"), None);
+}
+
+/// The text lines of a `<SYNC>` block, from the `<P>` of the lang class (or
+/// all).
+fn block_text(block: &str, lang: Option<&str>) -> Vec<String> {
+    let lower = block.to_ascii_lowercase();
+    let mut paragraphs: Vec<(Option<&str>, &str)> = Vec::new();
+    let mut starts: Vec<usize> = lower
+        .match_indices("<p")
+        .map(|(i, _)| i)
+        .filter(|&i| lower[i + 2..].starts_with(|c: char| c == '>' || c.is_whitespace()))
+        .collect();
+    if starts.is_empty() {
+        paragraphs.push((None, block));
+    }
+    starts.push(block.len());
+    for w in starts.windows(2) {
+        let p = &block[w[0]..w[1]];
+        match p.find('>') {
+            Some(end) => paragraphs.push((attribute(&p[..=end], "class"), &p[end + 1..])),
+            None => paragraphs.push((None, p)),
+        }
+    }
+
+    paragraphs
+        .into_iter()
+        .filter(|(class, _)| match (lang, class) {
+            (Some(lang), Some(class)) => lang.eq_ignore_ascii_case(class),
+            _ => true,
+        })
+        .flat_map(|(_, text)| split_br(&text.replace(['\r', '\n'], " ")))
+        .map(|l| decode_entities(&remove_end_p(&l)).trim().to_string())
+        .filter(|l| !l.is_empty())
+        .collect()
+}
+
+/// Split the text at each `<br>`, `<br/>` or `<br />`.
+fn split_br(text: &str) -> Vec<String> {
+    super::tags::map_tags(text, false, |tag| {
+        let name = tag[1..tag.len() - 1].trim().trim_end_matches('/').trim();
+        match name.eq_ignore_ascii_case("br") {
+            true => String::from("\n"),
+            false => tag.to_string(),
+        }
+    })
+    .split('\n')
+    .map(String::from)
+    .collect()
+}
+
+/// Remove the `</P>` end tags.
+fn remove_end_p(line: &str) -> String {
+    super::tags::map_tags(line, false, |tag| match tag.eq_ignore_ascii_case("</p>") {
+        true => String::new(),
+        false => tag.to_string(),
+    })
+}
+
+/// Decode the HTML entities, like `&amp;`, `&nbsp;` or `&#233;`.
+fn decode_entities(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(i) = rest.find('&') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i..];
+        let end = match rest[1..].find(|c: char| c == ';' || c == '&' || c.is_whitespace()) {
+            Some(end) if rest[1 + end..].starts_with(';') => end + 1,
+            _ => {
+                out.push('&');
+                rest = &rest[1..];
+                continue;
+            }
+        };
+        let name = &rest[1..end];
+        let decoded = match name.to_ascii_lowercase().as_str() {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            "nbsp" => Some(' '),
+            n if n.starts_with("#x") => u32::from_str_radix(&n[2..], 16)
+                .ok()
+                .and_then(char::from_u32),
+            n if n.starts_with('#') => n[1..].parse().ok().and_then(char::from_u32),
+            _ => None,
+        };
+        match decoded {
+            Some(c) => out.push(c),
+            None => out.push_str(&rest[..=end]),
+        }
+        rest = &rest[end + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+#[test]
+fn test_decode_entities() {
+    assert_eq!(
+        decode_entities("a &amp; b &lt;i&gt; &#233;&#xE9; &unknown; &"),
+        "a & b <i> éé &unknown; &"
+    );
+}
@@ -0,0 +1,257 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, Format, LineNb, ParseError, SubtitleParser};
+use std::fmt::Display;
+use std::io::{self, BufReader, Read, Write};
+use std::time::Duration;
+
+/// A parser of a YouTube SBV stream.
+pub struct SbvParser<R: Read> {
+    lines: LineNb<BufReader<R>>,
+    end: bool,
+}
+impl<R: Read> SbvParser<R> {
+    pub fn new(r: R) -> io::Result<Self> {
+        use std::io::BufRead;
+
+        let mut input = BufReader::new(r);
+
+        let first = input.fill_buf()?;
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
+            input.consume(3);
+        }
+
+        Ok(Self {
+            lines: LineNb::new(input),
+            end: false,
+        })
+    }
+    /// Return the text of a cue.
+    fn next_text(&mut self) -> io::Result<Vec<String>> {
+        let mut text = Vec::new();
+        loop {
+            match self.lines.next() {
+                Some(Err(e)) => return Err(e),
+                None => return Ok(text),
+                Some(Ok(l)) if l.is_empty() => return Ok(text),
+                Some(Ok(l)) => text.push(l),
+            }
+        }
+    }
+}
+impl<R: Read> SubtitleParser for SbvParser<R> {
+    fn format(&self) -> Format {
+        Format::Sbv
+    }
+}
+impl<R: Read> Iterator for SbvParser<R> {
+    type Item = io::Result<Cue>;
+    fn next(&mut self) -> Option<io::Result<Cue>> {
+        if self.end {
+            return None;
+        }
+
+        // Skip the blank lines between the cues.
+        let time_code = loop {
+            match self.lines.next() {
+                None => {
+                    self.end = true;
+                    return None;
+                }
+                Some(Err(e)) => {
+                    self.end = true;
+                    return Some(Err(e));
+                }
+                Some(Ok(l)) if l.is_empty() => continue,
+                Some(Ok(l)) => break l,
+            }
+        };
+
+        let cue = parse_time(&time_code, self.lines.current())
+            .and_then(|(begin, end)| Ok(Cue::new(None, begin, end, self.next_text()?)));
+        if cue.is_err() {
+            self.end = true;
+        }
+        Some(cue.map_err(|e| self.lines.locate(e)))
+    }
+}
+#[test]
+fn sbvparser() {
+    let mut p = SbvParser::new(
+        "0:00:01.000,0:00:04.000
+Never drink liquid nitrogen.
+
+0:00:05.000,0:00:09.500
+— It will perforate your stomach.
+— You could die."
+            .as_bytes(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(1, 0),
+            Duration::new(4, 0),
+            vec![String::from("Never drink liquid nitrogen.")],
+        )
+    );
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            None,
+            Duration::new(5, 0),
+            Duration::new(9, 500_000_000),
+            vec![
+                String::from("— It will perforate your stomach."),
+                String::from("— You could die."),
+            ],
+        )
+    );
+    assert!(p.next().is_none());
+
+    // Many blank lines do not overflow the stack.
+    let input = format!("{}0:00:01.000,0:00:02.000\nHello\n", "\n".repeat(1_000_000));
+    let mut p = SbvParser::new(input.as_bytes()).unwrap();
+    assert_eq!(p.next().unwrap().unwrap().text, vec![String::from("Hello")]);
+    assert!(p.next().is_none());
+}
+
+/// Parse the `begin,end` time line.
+fn parse_time(s: &str, line: usize) -> io::Result<(Duration, Duration)> {
+    let split: Vec<&str> = s.split(',').take(3).collect();
+    if split.len() != 2 {
+        return ParseError::timestamp("Invalide time code syntax", s, line);
+    }
+
+    Ok((
+        parse_duration(split[0].trim(), line)?,
+        parse_duration(split[1].trim(), line)?,
+    ))
+}
+
+/// Parse a `h:mm:ss.mmm` time code.
+fn parse_duration(s: &str, line: usize) -> io::Result<Duration> {
+    let split: Vec<&str> = s.split(':').take(4).collect();
+    if split.len() != 3 {
+        return ParseError::timestamp("Invalid duration syntax", s, line);
+    }
+
+    let second_part: Vec<&str> = split[2].split('.').take(3).collect();
+    if second_part.len() != 2 {
+        return ParseError::timestamp(
+            "Invalid duration syntax (second and millisecond part)",
+            s,
+            line,
+        );
+    }
+
+    fn parse<T: std::str::FromStr>(s: &str, line: usize) -> io::Result<T>
+    where
+        <T as std::str::FromStr>::Err: Display,
+    {
+        s.parse().or_else(|e| ParseError::timestamp(e, s, line))
+    }
+    let hour: u64 = parse(split[0], line)?;
+    let min: u64 = parse(split[1], line)?;
+    let sec: u64 = parse(second_part[0], line)?;
+    let ms: u32 = parse(second_part[1], line)?;
+    if ms > 999 {
+        return ParseError::timestamp("millisecond greater than 999", s, line);
+    }
+
+    Ok(Duration::new(hour * 3600 + min * 60 + sec, ms * 1_000_000))
+}
+#[test]
+fn test_parse_duration() {
+    assert_eq!(
+        Duration::new(3600 + 23 * 60 + 17, 486 * 1_000_000),
+        parse_duration("1:23:17.486", 0).unwrap()
+    );
+}
+
+/// Write all Cues from the input Iterator into the write W. Use SBV subtitle format.
+/// The cue id are ignored. Return the number fo writed cue.
+pub fn out<I, W>(cues: I, mut w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut nb = 0;
+
+    for c in cues {
+        if nb > 0 {
+            writeln!(w)?;
+        }
+        write_duration(&mut w, &c.begin)?;
+        write!(w, ",")?;
+        write_duration(&mut w, &c.end)?;
+        writeln!(w)?;
+        for l in c.text {
+            writeln!(w, "{}", l)?;
+        }
+        nb += 1;
+    }
+
+    Ok(nb)
+}
+#[test]
+fn test_out() {
+    fn dur(d: u64) -> Duration {
+        Duration::new(d, 0)
+    }
+
+    let cues = vec![
+        Cue::new(
+            Some(String::from("ignored")),
+            dur(1),
+            dur(4),
+            vec!["Never drink liquid nitrogen.".to_string()],
+        ),
+        Cue::new(
+            None,
+            dur(5),
+            dur(9),
+            vec![
+                "— It will perforate your stomach.".to_string(),
+                "— You could die.".to_string(),
+            ],
+        ),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out(cues.into_iter(), &mut output).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "0:00:01.000,0:00:04.000
+Never drink liquid nitrogen.
+
+0:00:05.000,0:00:09.000
+— It will perforate your stomach.
+— You could die.
+"
+    );
+}
+
+/// Write one time code.
+fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), io::Error> {
+    let sec = d.as_secs();
+    write!(
+        w,
+        "{}:{:02}:{:02}.{:03}",
+        sec / 3600,
+        sec / 60 % 60,
+        sec % 60,
+        d.subsec_millis()
+    )
+}
+#[test]
+fn test_write_duration() {
+    let d = Duration::new(2 * 3600 + 3 * 60 + 5, 84 * 1_000_000);
+    let mut out: Vec<u8> = Vec::new();
+    write_duration(&mut out, &d).unwrap();
+    assert_eq!(std::str::from_utf8(&out).unwrap(), "2:03:05.084");
+}
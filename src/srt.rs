@@ -2,14 +2,21 @@
 // Use of this source code is governed by a BSD
 // license that can be found in the LICENSE file.
 
-use super::{Cue, LineNb};
+use super::{parse_nanos, Cue, Format, LineNb, ParseError, SubtitleParser, WriteOptions};
 use std::fmt::Display;
-use std::io::{self, BufReader, ErrorKind, Read, Write};
+use std::io::{self, BufReader, Read, Write};
 use std::time::Duration;
 
 pub struct SrtParser<R: Read> {
     lines: LineNb<BufReader<R>>,
     end: bool,
+    keep_ids: bool,
+    /// Accept a non-numeric id line.
+    lenient: bool,
+    /// Detect a cue without a blank line before it.
+    no_blank_lines: bool,
+    /// The lines readed but not yet parsed, the next line is the last.
+    pending: Vec<String>,
 }
 impl<R: Read> SrtParser<R> {
     pub fn new(r: R) -> io::Result<Self> {
@@ -18,29 +25,58 @@ impl<R: Read> SrtParser<R> {
         let mut input = BufReader::new(r);
 
         let first = input.fill_buf()?;
-        if first.len() >= 3 && &first[..3] == [0xEF, 0xBB, 0xBF] {
+        if first.len() >= 3 && first[..3] == [0xEF, 0xBB, 0xBF] {
             input.consume(3);
         }
 
         Ok(Self {
             lines: LineNb::new(input),
             end: false,
+            keep_ids: false,
+            lenient: false,
+            no_blank_lines: false,
+            pending: Vec::new(),
         })
     }
+    /// Keep the cue number as the `Cue.id`. Default to false, so the WebVTT
+    /// output has no numeric id.
+    pub fn keep_ids(mut self, keep: bool) -> Self {
+        self.keep_ids = keep;
+        self
+    }
+    /// In lenient mode, the parser accept a non-numeric id line, like `a1`,
+    /// keeped as the `Cue.id`. Default to strict.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+    /// Detect the begin of a cue in the text of the previous cue, for the
+    /// files without blank line between the cues: a numeric line followed by
+    /// a time code line. Default to false.
+    pub fn no_blank_lines(mut self, detect: bool) -> Self {
+        self.no_blank_lines = detect;
+        self
+    }
+    /// The next pending line, or else the next readed line.
+    fn next_line(&mut self) -> Option<io::Result<String>> {
+        self.pending.pop().map(Ok).or_else(|| self.lines.next())
+    }
     /// Just after the id line is readed, parse the cue (time code and text content).
-    fn next_cue(&mut self) -> io::Result<Cue> {
-        match self.lines.next() {
-            None => Err(io::Error::new(
-                ErrorKind::UnexpectedEof,
-                format!(
-                    "Expected time code to a new cue (line: {})",
-                    self.lines.current()
-                ),
-            )),
+    fn next_cue(&mut self, id: String) -> io::Result<Cue> {
+        match self.next_line() {
+            None => Err(ParseError::UnexpectedEof {
+                line: self.lines.current(),
+                offset: None,
+            }
+            .into()),
             Some(Err(e)) => Err(e),
             Some(Ok(time_code)) => {
-                let (begin, end) = parse_time(&time_code, self.lines.current())?;
-                Ok(Cue::new(None, begin, end, self.next_text()?))
+                let (begin, end, coordinates) = parse_time(&time_code, self.lines.current())?;
+                let numeric = id.chars().all(|c| c.is_numeric());
+                let id = Some(id).filter(|_| self.keep_ids || !numeric);
+                let mut c = Cue::new(id, begin, end, self.next_text()?);
+                c.coordinates = coordinates;
+                Ok(c)
             }
         }
     }
@@ -48,15 +84,42 @@ impl<R: Read> SrtParser<R> {
     fn next_text(&mut self) -> io::Result<Vec<String>> {
         let mut text = Vec::new();
         loop {
-            match self.lines.next() {
+            match self.next_line() {
                 Some(Err(e)) => return Err(e),
                 None => return Ok(text),
-                Some(Ok(l)) if l.len() == 0 => return Ok(text),
+                Some(Ok(l)) if l.is_empty() => return Ok(text),
+                Some(Ok(l)) if self.no_blank_lines && l.chars().all(|c| c.is_numeric()) => {
+                    match self.next_line() {
+                        Some(Err(e)) => return Err(e),
+                        Some(Ok(next)) if next.contains(" --> ") => {
+                            self.pending.push(next);
+                            self.pending.push(l);
+                            return Ok(text);
+                        }
+                        Some(Ok(next)) => {
+                            text.push(l);
+                            self.pending.push(next);
+                        }
+                        None => text.push(l),
+                    }
+                }
                 Some(Ok(l)) => text.push(l),
             }
         }
     }
 }
+impl<'a> SrtParser<Box<dyn Read + 'a>> {
+    /// Create a parser of a boxed reader, so its type does not depend on the
+    /// reader type, like when the input is chosen at runtime.
+    pub fn new_boxed<R: Read + 'a>(r: R) -> io::Result<Self> {
+        Self::new(Box::new(r))
+    }
+}
+impl<R: Read> SubtitleParser for SrtParser<R> {
+    fn format(&self) -> Format {
+        Format::Srt
+    }
+}
 impl<R: Read> Iterator for SrtParser<R> {
     type Item = io::Result<Cue>;
     fn next(&mut self) -> Option<io::Result<Cue>> {
@@ -64,7 +127,14 @@ impl<R: Read> Iterator for SrtParser<R> {
             return None;
         }
 
-        match self.lines.next() {
+        // A BOM can begin an id line in the concatenated files.
+        let line = self.next_line().map(|l| {
+            l.map(|l| match l.starts_with('\u{FEFF}') {
+                true => l.trim_start_matches('\u{FEFF}').to_string(),
+                false => l,
+            })
+        });
+        match line {
             None => {
                 self.end = true;
                 None
@@ -73,15 +143,16 @@ impl<R: Read> Iterator for SrtParser<R> {
                 self.end = true;
                 Some(Err(e))
             }
-            Some(Ok(l)) if l.len() == 0 => self.next(),
-            Some(Ok(id)) if id.chars().any(|c| !c.is_numeric()) => {
+            Some(Ok(l)) if l.is_empty() => self.next(),
+            Some(Ok(id)) if !self.lenient && id.chars().any(|c| !c.is_numeric()) => {
                 self.end = true;
-                Some(err_invalid("Unexpected line", &id, self.lines.current()))
+                let e = ParseError::unexpected::<Cue>(&id, self.lines.current()).unwrap_err();
+                Some(Err(self.lines.locate(e)))
             }
-            Some(Ok(..)) => match self.next_cue() {
+            Some(Ok(id)) => match self.next_cue(id) {
                 Err(e) => {
                     self.end = true;
-                    Some(Err(e))
+                    Some(Err(self.lines.locate(e)))
                 }
                 Ok(c) => Some(Ok(c)),
             },
@@ -107,7 +178,7 @@ fn srtparser() {
 
     let mut input: Vec<u8> = vec![0xEF, 0xBB, 0xBF];
     input
-        .write(
+        .write_all(
             b"1
 00:00:05,542 --> 00:00:07,792
 Hello
@@ -119,18 +190,166 @@ World
     t(&input[3..]);
     t(&input[..]);
 }
+#[test]
+fn srtparser_bom_in_file() {
+    let input = "1\n00:00:01,000 --> 00:00:02,000\nHello\n\n\u{FEFF}0002\n00:00:03,000 --> 00:00:04,000\nWorld\n";
+    let cues = SrtParser::new(input.as_bytes())
+        .unwrap()
+        .collect::<io::Result<Vec<Cue>>>()
+        .unwrap();
+    assert_eq!(
+        cues,
+        vec![
+            Cue::new(
+                None,
+                Duration::new(1, 0),
+                Duration::new(2, 0),
+                vec![String::from("Hello")]
+            ),
+            Cue::new(
+                None,
+                Duration::new(3, 0),
+                Duration::new(4, 0),
+                vec![String::from("World")]
+            ),
+        ]
+    );
+}
+#[test]
+fn srtparser_lenient() {
+    let input = "a1\n00:00:05,542 --> 00:00:07,792\nHello\n";
+    assert!(SrtParser::new(input.as_bytes())
+        .unwrap()
+        .next()
+        .unwrap()
+        .is_err());
+
+    let mut p = SrtParser::new(input.as_bytes()).unwrap().lenient(true);
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            Some(String::from("a1")),
+            Duration::new(5, 542_000_000),
+            Duration::new(7, 792_000_000),
+            vec![String::from("Hello")]
+        )
+    );
+    assert!(p.next().is_none());
+
+    let mut p = SrtParser::new("a1\nHello\n".as_bytes())
+        .unwrap()
+        .lenient(true);
+    assert!(p.next().unwrap().is_err());
+}
+#[test]
+fn srtparser_no_blank_lines() {
+    let input = "1
+00:00:01,000 --> 00:00:02,000
+Room
+101
+2
+00:00:03,000 --> 00:00:04,000
+Hello
+";
+    let cue = |begin: u64, end: u64, text: &[&str]| {
+        Cue::new(
+            None,
+            Duration::new(begin, 0),
+            Duration::new(end, 0),
+            text.iter().map(|t| t.to_string()).collect(),
+        )
+    };
+
+    let cues: Vec<Cue> = SrtParser::new(input.as_bytes())
+        .unwrap()
+        .map(|c| c.unwrap())
+        .collect();
+    assert_eq!(
+        cues,
+        vec![cue(
+            1,
+            2,
+            &["Room", "101", "2", "00:00:03,000 --> 00:00:04,000", "Hello"]
+        )]
+    );
+
+    let cues: Vec<Cue> = SrtParser::new(input.as_bytes())
+        .unwrap()
+        .no_blank_lines(true)
+        .map(|c| c.unwrap())
+        .collect();
+    assert_eq!(
+        cues,
+        vec![cue(1, 2, &["Room", "101"]), cue(3, 4, &["Hello"])]
+    );
+}
+#[test]
+fn srtparser_keep_ids() {
+    let input = "42\n00:00:05,542 --> 00:00:07,792\nHello\n";
+    let mut p = SrtParser::new(input.as_bytes()).unwrap().keep_ids(true);
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        Cue::new(
+            Some(String::from("42")),
+            Duration::new(5, 542_000_000),
+            Duration::new(7, 792_000_000),
+            vec![String::from("Hello")]
+        )
+    );
+}
 
-fn parse_time(s: &str, line: usize) -> io::Result<(Duration, Duration)> {
+/// Parse the time code line, with the optional coordinates after the end,
+/// like `00:00:05,542 --> 00:00:07,792 X1:100 X2:200 Y1:300 Y2:400`.
+fn parse_time(s: &str, line: usize) -> io::Result<(Duration, Duration, Option<String>)> {
     let split: Vec<&str> = s.split(" --> ").take(3).collect();
     if split.len() != 2 {
-        return err_invalid("Invalide time code syntax", s, line);
+        return ParseError::timestamp("Invalide time code syntax", s, line);
     }
 
+    let end = split[1].trim();
+    let (end, coordinates) = match end.find(char::is_whitespace) {
+        Some(i) => (&end[..i], Some(end[i..].trim_start().to_string())),
+        None => (end, None),
+    };
     Ok((
         parse_duration(split[0].trim_end(), line)?,
-        parse_duration(split[1].trim_start(), line)?,
+        parse_duration(end, line)?,
+        coordinates,
     ))
 }
+#[test]
+fn srtparser_errors() {
+    fn err(s: &str) -> ParseError {
+        let e = SrtParser::new(s.as_bytes())
+            .unwrap()
+            .next()
+            .unwrap()
+            .unwrap_err();
+        *e.into_inner().unwrap().downcast::<ParseError>().unwrap()
+    }
+
+    assert!(matches!(
+        err("1\n00:00:05 --> 00:00:07,792\n"),
+        ParseError::BadTimestamp {
+            line: 2,
+            offset: Some(2),
+            raw: Some(raw),
+            ..
+        } if raw == "00:00:05 --> 00:00:07,792"
+    ));
+    assert!(matches!(
+        err("Hello\n"),
+        ParseError::UnexpectedLine { line: 1, offset: Some(0), content, .. } if content == "Hello"
+    ));
+    assert!(matches!(
+        err("1\n"),
+        ParseError::UnexpectedEof {
+            line: 1,
+            offset: Some(0)
+        }
+    ));
+}
+
 #[test]
 fn parse_time_test() {
     fn dur(h: u64, m: u64, s: u64, ms: u32) -> Duration {
@@ -138,19 +357,57 @@ fn parse_time_test() {
     }
     assert_eq!(
         parse_time("17:35:29,942 --> 17:25:48,456", 0).unwrap(),
-        (dur(17, 35, 29, 942), dur(17, 25, 48, 456))
+        (dur(17, 35, 29, 942), dur(17, 25, 48, 456), None)
+    );
+    assert_eq!(
+        parse_time(
+            "00:00:05,542 --> 00:00:07,792  X1:100 X2:200 Y1:300 Y2:400",
+            0
+        )
+        .unwrap(),
+        (
+            dur(0, 0, 5, 542),
+            dur(0, 0, 7, 792),
+            Some(String::from("X1:100 X2:200 Y1:300 Y2:400"))
+        )
+    );
+    assert!(parse_time("00:00:05,542 --> 00:00:07,792X1:100", 0).is_err());
+}
+#[test]
+fn srtparser_coordinates() {
+    let srt = "1\n00:00:05,542 --> 00:00:07,792 X1:100 X2:200 Y1:300 Y2:400\nHello\n\n";
+    let c = SrtParser::new(srt.as_bytes())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap();
+    assert_eq!(
+        c.coordinates.as_deref(),
+        Some("X1:100 X2:200 Y1:300 Y2:400")
+    );
+    assert_eq!(c.text, vec!["Hello"]);
+
+    let mut output: Vec<u8> = Vec::new();
+    out(std::iter::once(c.clone()), &mut output).unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), srt);
+
+    let mut output: Vec<u8> = Vec::new();
+    super::webvtt_out(std::iter::once(c), &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "WEBVTT\n\n00:05.542 --> 00:07.792\nHello\n\n"
     );
 }
 
 fn parse_duration(s: &str, line: usize) -> io::Result<Duration> {
     let split: Vec<&str> = s.split(":").take(4).collect();
     if split.len() != 3 {
-        return err_invalid("Invalid duration syntax", s, line);
+        return ParseError::timestamp("Invalid duration syntax", s, line);
     }
 
     let second_part: Vec<&str> = split[2].split(",").take(3).collect();
     if second_part.len() != 2 {
-        return err_invalid(
+        return ParseError::timestamp(
             "Invalid duration syntax (second and microsecond part)",
             s,
             line,
@@ -161,22 +418,17 @@ fn parse_duration(s: &str, line: usize) -> io::Result<Duration> {
     where
         <T as std::str::FromStr>::Err: Display,
     {
-        s.parse().map_err(|e| {
-            io::Error::new(
-                ErrorKind::InvalidData,
-                format!("{} in {:?} (line {})", e, s, line),
-            )
-        })
+        s.parse().or_else(|e| ParseError::timestamp(e, s, line))
     }
     let hour: u64 = parse(split[0], line)?;
     let min: u64 = parse(split[1], line)?;
     let sec: u64 = parse(second_part[0], line)?;
-    let ms: u32 = parse(second_part[1], line)?;
-    if ms > 999 {
-        return err_invalid("microsecond greater than 999 ", s, line);
-    }
+    let nanos: u32 = match parse_nanos(second_part[1]) {
+        Some(nanos) => nanos,
+        None => return ParseError::timestamp("Need digits for the fraction of second", s, line),
+    };
 
-    Ok(Duration::new(hour * 3600 + min * 60 + sec, ms * 1_000_000))
+    Ok(Duration::new(hour * 3600 + min * 60 + sec, nanos))
 }
 #[test]
 fn test_parse_duration_test() {
@@ -184,36 +436,101 @@ fn test_parse_duration_test() {
         Duration::new(3600 + 23 * 60 + 17, 486 * 1_000_000),
         parse_duration("01:23:17,486", 0).unwrap()
     );
-}
-
-/// Create a io::Result with an error where the error kind is InvalidData.
-fn err_invalid<T>(because: &'static str, data: &str, line: usize) -> io::Result<T> {
-    Err(io::Error::new(
-        ErrorKind::InvalidData,
-        format!("{} in {:?} (line {})", because, data, line),
-    ))
+    assert_eq!(
+        Duration::new(5, 500_000_000),
+        parse_duration("00:00:05,5", 0).unwrap()
+    );
+    assert_eq!(
+        Duration::new(5, 540_000_000),
+        parse_duration("00:00:05,54", 0).unwrap()
+    );
+    assert_eq!(
+        Duration::new(5, 54_000_000),
+        parse_duration("00:00:05,054", 0).unwrap()
+    );
+    assert_eq!(
+        Duration::new(5, 123_456_000),
+        parse_duration("00:00:05,123456", 0).unwrap()
+    );
+    assert!(parse_duration("00:00:05,", 0).is_err());
 }
 
 /// Write all Cues from the input Iterator into the write W. Use SRT subtitle format.
 /// Return the number fo writed cue.
-pub fn out<I, W>(cues: I, mut w: W) -> Result<usize, std::io::Error>
+pub fn out<I, W>(cues: I, w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    out_with(cues, w, &WriteOptions::default())
+}
+
+/// Like `out`, but the first cue is numbered `start`, to continue the
+/// numbering of a previous file.
+pub fn out_numbered<I, W>(cues: I, w: W, start: usize) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    write_cues(cues, w, &WriteOptions::default(), start)
+}
+#[test]
+fn test_out_numbered() {
+    let cues = vec![
+        Cue::new(None, Duration::new(1, 0), Duration::new(2, 0), vec![]),
+        Cue::new(None, Duration::new(3, 0), Duration::new(4, 0), vec![]),
+    ];
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(2, out_numbered(cues.into_iter(), &mut output, 501).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "501\n00:00:01,000 --> 00:00:02,000\n\n502\n00:00:03,000 --> 00:00:04,000\n\n"
+    );
+}
+
+/// Like `out` with the writing options.
+pub fn out_with<I, W>(cues: I, w: W, options: &WriteOptions) -> Result<usize, std::io::Error>
 where
     W: Write,
     I: std::iter::Iterator<Item = Cue>,
 {
+    write_cues(cues, w, options, 1)
+}
+
+/// Write the cues numbered from start.
+fn write_cues<I, W>(
+    cues: I,
+    w: W,
+    options: &WriteOptions,
+    start: usize,
+) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut w = options.line_ending.writer(w);
     let mut nb = 0;
 
     for c in cues {
+        if nb > 0 {
+            writeln!(w)?;
+        }
+        writeln!(w, "{}", start + nb)?;
         nb += 1;
-        writeln!(w, "{}", nb)?;
-        write_duration(&mut w, &c.begin)?;
+        let sep = if options.srt_dot { '.' } else { ',' };
+        write_duration(&mut w, &c.begin, sep)?;
         write!(w, " --> ")?;
-        write_duration(&mut w, &c.end)?;
-        writeln!(w, "")?;
+        write_duration(&mut w, &c.end, sep)?;
+        if let Some(coordinates) = c.coordinates {
+            write!(w, " {}", coordinates)?;
+        }
+        writeln!(w)?;
         for l in c.text {
-            writeln!(w, "{}", l)?;
+            writeln!(w, "{}", options.tags.apply(&l, Format::Srt))?;
         }
-        writeln!(w, "")?;
+    }
+    if nb > 0 && !options.no_trailing_newline {
+        writeln!(w)?;
     }
 
     Ok(nb)
@@ -243,7 +560,7 @@ fn test_out() {
     ];
 
     let mut output: Vec<u8> = Vec::new();
-    assert_eq!(2, out(cues.into_iter(), &mut output).unwrap());
+    assert_eq!(2, out(cues.clone().into_iter(), &mut output).unwrap());
     assert_eq!(
         std::str::from_utf8(&output).unwrap(),
         "1
@@ -255,19 +572,39 @@ Never drink liquid nitrogen.
 — It will perforate your stomach.
 — You could die.
 
+"
+    );
+
+    let options = WriteOptions {
+        no_trailing_newline: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    out_with(cues.into_iter(), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "1
+00:00:01,000 --> 00:00:04,000
+Never drink liquid nitrogen.
+
+2
+00:00:05,000 --> 00:00:09,000
+— It will perforate your stomach.
+— You could die.
 "
     );
 }
 
-/// Write one time code to a line.
-fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), io::Error> {
+/// Write one time code to a line, with sep before the milliseconds.
+fn write_duration<W: Write>(w: &mut W, d: &Duration, sep: char) -> Result<(), io::Error> {
     let sec = d.as_secs();
     write!(
         w,
-        "{:02}:{:02}:{:02},{:03}",
+        "{:02}:{:02}:{:02}{}{:03}",
         sec / 3600,
         sec / 60 % 60,
         sec % 60,
+        sep,
         d.subsec_millis()
     )
 }
@@ -275,6 +612,33 @@ fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), io::Error> {
 fn test_write_duration() {
     let d = Duration::new(2 * 3600 + 3 * 60 + 5, 84 * 1_000_000);
     let mut out: Vec<u8> = Vec::new();
-    write_duration(&mut out, &d).unwrap();
+    write_duration(&mut out, &d, ',').unwrap();
     assert_eq!(std::str::from_utf8(&out).unwrap(), "02:03:05,084");
 }
+#[test]
+fn test_out_dot() {
+    let cues = vec![Cue::new(
+        None,
+        Duration::new(2 * 3600 + 3 * 60 + 5, 84 * 1_000_000),
+        Duration::new(2 * 3600 + 3 * 60 + 6, 0),
+        vec![String::from("Hello")],
+    )];
+    let options = WriteOptions {
+        srt_dot: true,
+        ..WriteOptions::default()
+    };
+
+    let mut out: Vec<u8> = Vec::new();
+    out_with(cues.clone().into_iter(), &mut out, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "1\n02:03:05.084 --> 02:03:06.000\nHello\n\n"
+    );
+
+    let mut out: Vec<u8> = Vec::new();
+    super::webvtt_out_with(cues.into_iter(), &mut out, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&out).unwrap(),
+        "WEBVTT\n\n02:03:05.084 --> 02:03:06.000\nHello\n\n"
+    );
+}
@@ -0,0 +1,222 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::Format;
+
+/// How the writers handle the inline markup tags of the cue text.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum TagStyle {
+    /// Write the tags as they are.
+    #[default]
+    Verbatim,
+    /// Remove all the tags.
+    Strip,
+    /// Convert the tags to the output format: `<font color="red">` become
+    /// `<c.red>` in WebVTT and the reverse in SRT.
+    Convert,
+}
+impl TagStyle {
+    /// Apply the tag style to one line for the output format.
+    pub(crate) fn apply(self, l: &str, format: Format) -> String {
+        match (self, format) {
+            (TagStyle::Verbatim, _) => l.to_string(),
+            (TagStyle::Strip, _) => map_tags(l, true, |_| String::new()),
+            (TagStyle::Convert, Format::WebVTT) => map_tags(l, false, to_webvtt),
+            (TagStyle::Convert, Format::Srt) => map_tags(l, false, to_srt),
+            (TagStyle::Convert, _) => l.to_string(),
+        }
+    }
+}
+#[test]
+fn tag_style() {
+    let l = "<i>Hello</i> <font color=\"red\">World</font>";
+    assert_eq!(TagStyle::Verbatim.apply(l, Format::WebVTT), l);
+    assert_eq!(TagStyle::Strip.apply(l, Format::WebVTT), "Hello World");
+    assert_eq!(
+        TagStyle::Convert.apply(l, Format::WebVTT),
+        "<i>Hello</i> <c.red>World</c>"
+    );
+    assert_eq!(
+        TagStyle::Convert.apply("<font color=\"#ff0000\">Red</font>", Format::WebVTT),
+        "<c>Red</c>"
+    );
+    assert_eq!(
+        TagStyle::Convert.apply(
+            "<v Bob><i>Hello</i> <c.red.loud>World</c><00:01.500>!</v>",
+            Format::Srt
+        ),
+        "<i>Hello</i> <font color=\"red\">World</font>!"
+    );
+}
+
+/// One line of a cue text, with the WebVTT voice span `<v Speaker>` parsed.
+/// The other tags, like the inline timestamps `<00:01.500>`, are keeped
+/// verbatim in the text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CueLine {
+    /// The voice name, `Bob` in `<v Bob>Hello`.
+    pub speaker: Option<String>,
+    /// The text without the voice tags.
+    pub text: String,
+}
+impl CueLine {
+    /// Parse one line of a cue text.
+    pub fn parse(l: &str) -> CueLine {
+        let start = l.trim_start();
+        let is_voice = start.starts_with("<v")
+            && start[2..].starts_with(|c: char| c == '.' || c == '>' || c.is_whitespace());
+        let end = match (is_voice, start.find('>')) {
+            (true, Some(end)) => end,
+            _ => {
+                return CueLine {
+                    speaker: None,
+                    text: l.to_string(),
+                }
+            }
+        };
+        let speaker = start[2..end]
+            .trim_start_matches(|c: char| !c.is_whitespace())
+            .trim();
+        let text = &start[end + 1..];
+        CueLine {
+            speaker: Some(speaker.to_string()).filter(|s| !s.is_empty()),
+            text: text.strip_suffix("</v>").unwrap_or(text).to_string(),
+        }
+    }
+}
+impl std::fmt::Display for CueLine {
+    /// Write the line as WebVTT, with the voice span if there is a speaker.
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.speaker {
+            Some(speaker) => write!(f, "<v {}>{}", speaker, self.text),
+            None => f.write_str(&self.text),
+        }
+    }
+}
+#[test]
+fn cue_line() {
+    assert_eq!(
+        CueLine::parse("<v Bob>Hello"),
+        CueLine {
+            speaker: Some(String::from("Bob")),
+            text: String::from("Hello"),
+        }
+    );
+    assert_eq!(
+        CueLine::parse("<v.loud Mary Ann>Hi <00:01.500>all</v>"),
+        CueLine {
+            speaker: Some(String::from("Mary Ann")),
+            text: String::from("Hi <00:01.500>all"),
+        }
+    );
+    assert_eq!(CueLine::parse("<i>Hello</i>").speaker, None);
+    assert_eq!(CueLine::parse("<video>").speaker, None);
+    assert_eq!(CueLine::parse("<v Bob>Hello").to_string(), "<v Bob>Hello");
+}
+
+/// Call f on each tag `<...>` (and `{...}` if braces) of the line, the tag is
+/// replaced by the result. A `<` or a `{` without end is keeped, so as a `<`
+/// followed by a space.
+pub(crate) fn map_tags(l: &str, braces: bool, f: impl FnMut(&str) -> String) -> String {
+    map_parts(l, braces, f, |t| t.to_string())
+}
+
+/// Like `map_tags`, but call f on each text between the tags, the tags are
+/// keeped.
+pub(crate) fn map_text(l: &str, braces: bool, f: impl FnMut(&str) -> String) -> String {
+    map_parts(l, braces, |t| t.to_string(), f)
+}
+#[test]
+fn test_map_text() {
+    let upper = |l: &str| map_text(l, false, |t| t.to_uppercase());
+    assert_eq!(
+        upper("<i>Hello</i> <v Bob>world"),
+        "<i>HELLO</i> <v Bob>WORLD"
+    );
+    assert_eq!(upper("1 < 2 and <b>more</b>"), "1 < 2 AND <b>MORE</b>");
+    assert_eq!(upper("1 <2 and more"), "1 <2 AND MORE");
+}
+
+/// Call tag on each tag and text on each text between the tags.
+fn map_parts(
+    l: &str,
+    braces: bool,
+    mut tag: impl FnMut(&str) -> String,
+    mut text: impl FnMut(&str) -> String,
+) -> String {
+    let mut out = String::with_capacity(l.len());
+    let mut rest = l;
+    // The length of the text at the begin of rest, before the next tag.
+    let mut len = 0;
+    while let Some(c) = rest[len..].chars().next() {
+        let t = &rest[len..];
+        let end = match c {
+            '<' => t[1..]
+                .find(['<', '>'])
+                .filter(|&i| t[1 + i..].starts_with('>'))
+                .filter(|_| !t[1..].starts_with(char::is_whitespace)),
+            '{' if braces => t[1..].find('}'),
+            _ => None,
+        };
+        match end {
+            Some(i) => {
+                if len > 0 {
+                    out.push_str(&text(&rest[..len]));
+                }
+                out.push_str(&tag(&t[..i + 2]));
+                rest = &t[i + 2..];
+                len = 0;
+            }
+            None => len += c.len_utf8(),
+        }
+    }
+    if len > 0 {
+        out.push_str(&text(rest));
+    }
+    out
+}
+
+/// Convert one SRT tag to WebVTT.
+fn to_webvtt(tag: &str) -> String {
+    let inner = tag[1..tag.len() - 1].trim();
+    let lower = inner.to_ascii_lowercase();
+    if lower == "/font" {
+        String::from("</c>")
+    } else if lower == "font" || lower.starts_with("font ") {
+        let color = lower.find("color=").map(|i| {
+            inner[i + 6..]
+                .trim_start_matches(['"', '\''])
+                .split(['"', '\'', ' '])
+                .next()
+                .unwrap_or("")
+        });
+        match color {
+            Some(c) if !c.is_empty() && c.chars().all(|c| c.is_ascii_alphanumeric()) => {
+                format!("<c.{}>", c)
+            }
+            _ => String::from("<c>"),
+        }
+    } else {
+        tag.to_string()
+    }
+}
+
+/// Convert one WebVTT tag to SRT.
+fn to_srt(tag: &str) -> String {
+    let inner = &tag[1..tag.len() - 1];
+    let name = inner
+        .trim_start_matches('/')
+        .split(['.', ' '])
+        .next()
+        .unwrap_or("");
+    match name {
+        "i" | "b" | "u" => tag.to_string(),
+        "c" if inner.starts_with('/') => String::from("</font>"),
+        "c" => match inner.split('.').nth(1) {
+            Some(color) => format!("<font color=\"{}\">", color),
+            None => String::from("<font>"),
+        },
+        _ => String::new(),
+    }
+}
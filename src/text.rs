@@ -0,0 +1,110 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::{Cue, WriteOptions};
+use std::io::Write;
+
+/// Write the text of all Cues from the input Iterator into the write W, one
+/// line per text line, without the times. It's a transcript, it can not be
+/// parsed back. Return the number fo writed cue.
+pub fn out<I, W>(cues: I, w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    out_with(cues, w, &WriteOptions::default())
+}
+
+/// Like `out` with the writing options: `merge_lines` writes each cue on one
+/// line and `collapse_lines` skips a line identical to the previous one.
+pub fn out_with<I, W>(cues: I, w: W, options: &WriteOptions) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    let mut w = options.line_ending.writer(w);
+    let mut nb = 0;
+    let mut previous: Option<String> = None;
+
+    for c in cues {
+        let lines = match options.merge_lines {
+            true => vec![c
+                .text
+                .iter()
+                .map(|l| l.trim())
+                .collect::<Vec<_>>()
+                .join(" ")],
+            false => c.text,
+        };
+        for l in lines {
+            if options.collapse_lines && previous.as_ref() == Some(&l) {
+                continue;
+            }
+            writeln!(w, "{}", l)?;
+            previous = Some(l);
+        }
+        nb += 1;
+    }
+
+    Ok(nb)
+}
+#[test]
+fn test_out() {
+    use std::time::Duration;
+
+    fn cue(begin: u64, text: &[&str]) -> Cue {
+        Cue::new(
+            None,
+            Duration::new(begin, 0),
+            Duration::new(begin + 1, 0),
+            text.iter().map(|t| t.to_string()).collect(),
+        )
+    }
+    let cues = vec![
+        cue(1, &["Never drink", "liquid nitrogen."]),
+        cue(2, &["liquid nitrogen.", "It will perforate"]),
+        cue(3, &["your stomach."]),
+    ];
+
+    let mut output: Vec<u8> = Vec::new();
+    assert_eq!(3, out(cues.clone().into_iter(), &mut output).unwrap());
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "Never drink
+liquid nitrogen.
+liquid nitrogen.
+It will perforate
+your stomach.
+"
+    );
+
+    let options = WriteOptions {
+        collapse_lines: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    out_with(cues.clone().into_iter(), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "Never drink
+liquid nitrogen.
+It will perforate
+your stomach.
+"
+    );
+
+    let options = WriteOptions {
+        merge_lines: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    out_with(cues.into_iter(), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "Never drink liquid nitrogen.
+liquid nitrogen. It will perforate
+your stomach.
+"
+    );
+}
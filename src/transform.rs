@@ -0,0 +1,1452 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::tags::{map_tags, map_text};
+use super::{Cue, Delta, Warning};
+use std::iter::Peekable;
+use std::time::Duration;
+
+/// Create a cue for the tests, begin and end are in milliseconds.
+#[cfg(test)]
+fn cue(begin: u64, end: u64, text: &[&str]) -> Cue {
+    Cue::new(
+        None,
+        Duration::from_millis(begin),
+        Duration::from_millis(end),
+        text.iter().map(|t| t.to_string()).collect(),
+    )
+}
+
+/// Merge a cue with the next ones while they begin before the end of the
+/// current cue plus the gap. The text lines are concatenated without the
+/// identical lines.
+pub fn merge_overlaps<I: Iterator<Item = Cue>>(
+    iter: I,
+    gap: Duration,
+) -> impl Iterator<Item = Cue> {
+    MergeOverlaps {
+        iter: iter.peekable(),
+        gap,
+    }
+}
+
+struct MergeOverlaps<I: Iterator<Item = Cue>> {
+    iter: Peekable<I>,
+    gap: Duration,
+}
+impl<I: Iterator<Item = Cue>> Iterator for MergeOverlaps<I> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let mut c = self.iter.next()?;
+        while let Some(next) = self.iter.peek() {
+            if next.begin >= c.end + self.gap {
+                break;
+            }
+            let next = self.iter.next().unwrap();
+            c.begin = c.begin.min(next.begin);
+            c.end = c.end.max(next.end);
+            for l in next.text {
+                if !c.text.contains(&l) {
+                    c.text.push(l);
+                }
+            }
+        }
+        Some(c)
+    }
+}
+#[test]
+fn test_merge_overlaps() {
+    let cues = vec![
+        cue(1000, 4000, &["Hello", "World"]),
+        cue(3000, 6000, &["World", "Yolo"]),
+        cue(20000, 25000, &["Far away"]),
+    ];
+    let merged: Vec<Cue> = merge_overlaps(cues.into_iter(), Duration::new(1, 0)).collect();
+    assert_eq!(
+        merged,
+        vec![
+            cue(1000, 6000, &["Hello", "World", "Yolo"]),
+            cue(20000, 25000, &["Far away"]),
+        ]
+    );
+}
+
+/// Merge the consecutive cues with the same text when the next one begins
+/// before the end of the previous one, the end is extended. The repeats not
+/// contiguous are keeped.
+pub fn dedupe<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    Dedupe {
+        iter: iter.peekable(),
+    }
+}
+
+struct Dedupe<I: Iterator<Item = Cue>> {
+    iter: Peekable<I>,
+}
+impl<I: Iterator<Item = Cue>> Iterator for Dedupe<I> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let mut c = self.iter.next()?;
+        while let Some(next) = self.iter.next_if(|n| n.text == c.text && c.end >= n.begin) {
+            c.end = c.end.max(next.end);
+        }
+        Some(c)
+    }
+}
+#[test]
+fn test_dedupe() {
+    let cues = vec![
+        cue(1000, 2000, &["Hello"]),
+        cue(2000, 3000, &["Hello"]),
+        cue(3000, 5000, &["Hello"]),
+        cue(5000, 6000, &["World"]),
+        cue(8000, 9000, &["World"]),
+    ];
+    assert_eq!(
+        dedupe(cues.into_iter()).collect::<Vec<Cue>>(),
+        vec![
+            cue(1000, 5000, &["Hello"]),
+            cue(5000, 6000, &["World"]),
+            cue(8000, 9000, &["World"])
+        ]
+    );
+}
+
+/// Remove the cues without text, or with only whitespace lines.
+pub fn drop_empty<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    iter.filter(|c| c.text.iter().any(|l| !l.trim().is_empty()))
+}
+#[test]
+fn test_drop_empty() {
+    let cues = vec![
+        cue(1000, 2000, &[]),
+        cue(1000, 2000, &["  ", ""]),
+        cue(1000, 2000, &["", "Hello"]),
+    ];
+    let kept: Vec<Cue> = drop_empty(cues.into_iter()).collect();
+    assert_eq!(kept, vec![cue(1000, 2000, &["", "Hello"])]);
+}
+
+/// Extend the end of the cues shorter than min, but never after the begin of
+/// the next cue.
+pub fn min_duration<I: Iterator<Item = Cue>>(iter: I, min: Duration) -> impl Iterator<Item = Cue> {
+    MinDuration {
+        iter: iter.peekable(),
+        min,
+    }
+}
+
+struct MinDuration<I: Iterator<Item = Cue>> {
+    iter: Peekable<I>,
+    min: Duration,
+}
+impl<I: Iterator<Item = Cue>> Iterator for MinDuration<I> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let mut c = self.iter.next()?;
+        let mut end = c.begin + self.min;
+        if let Some(next) = self.iter.peek() {
+            end = end.min(next.begin);
+        }
+        c.end = c.end.max(end);
+        Some(c)
+    }
+}
+#[test]
+fn test_min_duration() {
+    let cues = vec![
+        cue(0, 200, &[]),
+        cue(500, 600, &[]),
+        cue(5000, 7000, &[]),
+        cue(8000, 8100, &[]),
+    ];
+    let extended: Vec<Cue> = min_duration(cues.into_iter(), Duration::from_secs(1)).collect();
+    assert_eq!(
+        extended,
+        vec![
+            cue(0, 500, &[]),
+            cue(500, 1500, &[]),
+            cue(5000, 7000, &[]),
+            cue(8000, 9000, &[])
+        ]
+    );
+}
+
+/// What to do with the cues which end at their begin, see `zero_duration`.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum ZeroDurationPolicy {
+    /// Keep the cues as they are.
+    #[default]
+    Keep,
+    /// Remove the cues.
+    Drop,
+    /// Extend the cues to this duration, but never after the begin of the
+    /// next cue.
+    Extend(Duration),
+}
+
+/// Apply the policy on the cues with a zero duration, the other cues are not
+/// modified.
+pub fn zero_duration<I: Iterator<Item = Cue>>(
+    iter: I,
+    policy: ZeroDurationPolicy,
+) -> impl Iterator<Item = Cue> {
+    ZeroDuration {
+        iter: iter.peekable(),
+        policy,
+    }
+}
+
+struct ZeroDuration<I: Iterator<Item = Cue>> {
+    iter: Peekable<I>,
+    policy: ZeroDurationPolicy,
+}
+impl<I: Iterator<Item = Cue>> Iterator for ZeroDuration<I> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        loop {
+            let mut c = self.iter.next()?;
+            if c.end != c.begin {
+                return Some(c);
+            }
+            match self.policy {
+                ZeroDurationPolicy::Keep => {}
+                ZeroDurationPolicy::Drop => continue,
+                ZeroDurationPolicy::Extend(d) => {
+                    c.end = c.begin + d;
+                    if let Some(next) = self.iter.peek() {
+                        c.end = c.end.min(next.begin.max(c.begin));
+                    }
+                }
+            }
+            return Some(c);
+        }
+    }
+}
+#[test]
+fn test_zero_duration() {
+    let cues = vec![
+        cue(0, 0, &[]),
+        cue(200, 1000, &[]),
+        cue(3000, 3000, &[]),
+        cue(5000, 5000, &[]),
+    ];
+    let apply = |policy| zero_duration(cues.clone().into_iter(), policy).collect::<Vec<Cue>>();
+
+    assert_eq!(apply(ZeroDurationPolicy::Keep), cues);
+    assert_eq!(apply(ZeroDurationPolicy::Drop), vec![cue(200, 1000, &[])]);
+    assert_eq!(
+        apply(ZeroDurationPolicy::Extend(Duration::from_millis(500))),
+        vec![
+            cue(0, 200, &[]),
+            cue(200, 1000, &[]),
+            cue(3000, 3500, &[]),
+            cue(5000, 5500, &[])
+        ]
+    );
+}
+
+/// How `max_duration` shortens a long cue.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum MaxDurationMode {
+    /// Pull back the end of the cue.
+    #[default]
+    Truncate,
+    /// Split the cue into back-to-back cues with the same text, only the first
+    /// one keeps the id.
+    Split,
+}
+
+/// Shorten the cues longer than cap, see `MaxDurationMode`. A zero cap does
+/// not modify the cues.
+pub fn max_duration<I: Iterator<Item = Cue>>(
+    iter: I,
+    cap: Duration,
+    mode: MaxDurationMode,
+) -> impl Iterator<Item = Cue> {
+    iter.flat_map(move |c| {
+        if cap.is_zero() || c.duration() <= cap {
+            return vec![c];
+        }
+        match mode {
+            MaxDurationMode::Truncate => {
+                let mut c = c;
+                c.end = c.begin + cap;
+                vec![c]
+            }
+            MaxDurationMode::Split => {
+                let mut cues = Vec::new();
+                let mut begin = c.begin;
+                while begin < c.end {
+                    let mut part = c.clone();
+                    if begin != c.begin {
+                        part.id = None;
+                    }
+                    part.begin = begin;
+                    part.end = c.end.min(begin + cap);
+                    begin = part.end;
+                    cues.push(part);
+                }
+                cues
+            }
+        }
+    })
+}
+#[test]
+fn test_max_duration() {
+    let cap = Duration::new(7, 0);
+    let cues = vec![cue(10000, 30000, &["Hello"]), cue(40000, 45000, &["Hello"])];
+
+    let truncated: Vec<Cue> =
+        max_duration(cues.clone().into_iter(), cap, MaxDurationMode::Truncate).collect();
+    assert_eq!(
+        truncated,
+        vec![cue(10000, 17000, &["Hello"]), cue(40000, 45000, &["Hello"])]
+    );
+
+    let split: Vec<Cue> = max_duration(cues.into_iter(), cap, MaxDurationMode::Split).collect();
+    assert_eq!(
+        split,
+        vec![
+            cue(10000, 17000, &["Hello"]),
+            cue(17000, 24000, &["Hello"]),
+            cue(24000, 30000, &["Hello"]),
+            cue(40000, 45000, &["Hello"])
+        ]
+    );
+
+    let mut long = cue(10000, 20000, &["Hello"]);
+    long.id = Some(String::from("intro"));
+    let split: Vec<Cue> =
+        max_duration(std::iter::once(long), cap, MaxDurationMode::Split).collect();
+    let ids: Vec<Option<&str>> = split.iter().map(|c| c.id.as_deref()).collect();
+    assert_eq!(ids, vec![Some("intro"), None]);
+}
+
+/// Pull back the end of a cue to keep at least gap before the begin of the
+/// next cue. A cue too short to be pulled back is keeped as it is, see
+/// `enforce_gap_with` to get a warning.
+pub fn enforce_gap<I: Iterator<Item = Cue>>(iter: I, gap: Duration) -> impl Iterator<Item = Cue> {
+    enforce_gap_with(iter, gap, |_| {})
+}
+
+/// Like `enforce_gap`, warn is called with a `Warning::GapTooShort` for each
+/// cue too short to be pulled back.
+pub fn enforce_gap_with<I, F>(iter: I, gap: Duration, warn: F) -> impl Iterator<Item = Cue>
+where
+    I: Iterator<Item = Cue>,
+    F: FnMut(Warning),
+{
+    EnforceGap {
+        iter: iter.peekable(),
+        gap,
+        index: 0,
+        warn,
+    }
+}
+
+struct EnforceGap<I: Iterator<Item = Cue>, F: FnMut(Warning)> {
+    iter: Peekable<I>,
+    gap: Duration,
+    /// The number of the last returned cue, from 1.
+    index: usize,
+    warn: F,
+}
+impl<I: Iterator<Item = Cue>, F: FnMut(Warning)> Iterator for EnforceGap<I, F> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let mut c = self.iter.next()?;
+        self.index += 1;
+        if let Some(next) = self.iter.peek() {
+            let end = next.begin.checked_sub(self.gap).unwrap_or_default();
+            if end < c.end && end > c.begin {
+                c.end = end;
+            } else if end < c.end {
+                (self.warn)(Warning::GapTooShort { index: self.index });
+            }
+        }
+        Some(c)
+    }
+}
+#[test]
+fn test_enforce_gap() {
+    let cues = vec![
+        cue(0, 1000, &[]),
+        cue(1000, 1020, &[]),
+        cue(1030, 2000, &[]),
+        cue(3000, 4000, &[]),
+    ];
+    let mut warnings = Vec::new();
+    let spaced: Vec<Cue> = enforce_gap_with(cues.into_iter(), Duration::from_millis(40), |w| {
+        warnings.push(w)
+    })
+    .collect();
+    assert_eq!(
+        spaced,
+        vec![
+            cue(0, 960, &[]),
+            cue(1000, 1020, &[]),
+            cue(1030, 2000, &[]),
+            cue(3000, 4000, &[])
+        ]
+    );
+    assert_eq!(warnings, vec![Warning::GapTooShort { index: 2 }]);
+}
+
+/// Pull back the end of a cue to the begin of the next cue, if it's after.
+/// The end is not pulled before the begin of the cue. The last cue is not
+/// modified.
+pub fn trim_to_next<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    TrimToNext {
+        iter: iter.peekable(),
+    }
+}
+
+struct TrimToNext<I: Iterator<Item = Cue>> {
+    iter: Peekable<I>,
+}
+impl<I: Iterator<Item = Cue>> Iterator for TrimToNext<I> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let mut c = self.iter.next()?;
+        if let Some(next) = self.iter.peek() {
+            if c.end > next.begin {
+                c.end = next.begin.max(c.begin);
+            }
+        }
+        Some(c)
+    }
+}
+#[test]
+fn test_trim_to_next() {
+    let cues = vec![
+        cue(1000, 5000, &[]),
+        cue(4000, 6000, &[]),
+        cue(6000, 8000, &[]),
+        cue(9000, 12000, &[]),
+    ];
+    assert_eq!(
+        trim_to_next(cues.into_iter()).collect::<Vec<Cue>>(),
+        vec![
+            cue(1000, 4000, &[]),
+            cue(4000, 6000, &[]),
+            cue(6000, 8000, &[]),
+            cue(9000, 12000, &[])
+        ]
+    );
+}
+
+/// Merge two tracks sorted by time, like two languages, into one track sorted
+/// by time. Two cues whose begins are within tolerance are merged into one
+/// cue: the lines of a, the separator line, then the lines of b, from the
+/// first begin to the last end. The id and the settings come from the cue of
+/// a. An empty separator is a blank line, which ends the cue in SRT and
+/// WebVTT, so prefer a marker like `---`.
+pub fn merge_tracks<A, B>(
+    a: A,
+    b: B,
+    tolerance: Duration,
+    separator: &str,
+) -> impl Iterator<Item = Cue>
+where
+    A: Iterator<Item = Cue>,
+    B: Iterator<Item = Cue>,
+{
+    MergeTracks {
+        a: a.peekable(),
+        b: b.peekable(),
+        tolerance,
+        separator: separator.to_string(),
+    }
+}
+
+struct MergeTracks<A: Iterator<Item = Cue>, B: Iterator<Item = Cue>> {
+    a: Peekable<A>,
+    b: Peekable<B>,
+    tolerance: Duration,
+    separator: String,
+}
+impl<A: Iterator<Item = Cue>, B: Iterator<Item = Cue>> Iterator for MergeTracks<A, B> {
+    type Item = Cue;
+    fn next(&mut self) -> Option<Cue> {
+        let (a, b) = match (self.a.peek(), self.b.peek()) {
+            (None, None) => return None,
+            (Some(_), None) => return self.a.next(),
+            (None, Some(_)) => return self.b.next(),
+            (Some(a), Some(b)) => (a.begin, b.begin),
+        };
+        if a.abs_diff(b) > self.tolerance {
+            return match a <= b {
+                true => self.a.next(),
+                false => self.b.next(),
+            };
+        }
+
+        let mut c = self.a.next()?;
+        let other = self.b.next()?;
+        c.begin = c.begin.min(other.begin);
+        c.end = c.end.max(other.end);
+        c.text.push(self.separator.clone());
+        c.text.extend(other.text);
+        Some(c)
+    }
+}
+#[test]
+fn test_merge_tracks() {
+    let en = vec![cue(1000, 3000, &["Hello"]), cue(5000, 6000, &["Bye"])];
+    let fr = vec![cue(1100, 3200, &["Bonjour"]), cue(4000, 4500, &["Oui"])];
+    let merged: Vec<Cue> = merge_tracks(
+        en.into_iter(),
+        fr.into_iter(),
+        Duration::from_millis(200),
+        "---",
+    )
+    .collect();
+    let mut stacked = cue(1000, 3200, &["Hello"]);
+    stacked.text.push(String::from("---"));
+    stacked.text.push(String::from("Bonjour"));
+    assert_eq!(
+        merged,
+        vec![
+            stacked,
+            cue(4000, 4500, &["Oui"]),
+            cue(5000, 6000, &["Bye"])
+        ]
+    );
+
+    let merged: Vec<Cue> = merge_tracks(
+        vec![cue(1000, 3000, &["Hello"])].into_iter(),
+        vec![cue(1100, 3200, &["Bonjour"])].into_iter(),
+        Duration::ZERO,
+        "---",
+    )
+    .collect();
+    assert_eq!(
+        merged,
+        vec![cue(1000, 3000, &["Hello"]), cue(1100, 3200, &["Bonjour"])]
+    );
+}
+
+/// Return a `[gap]` cue for each blank time longer than min between the
+/// cues, the cues themselves are not returned. The cues must be sorted.
+pub fn gaps<I: Iterator<Item = Cue>>(iter: I, min: Duration) -> impl Iterator<Item = Cue> {
+    let mut prev_end: Option<Duration> = None;
+    iter.filter_map(move |c| {
+        let gap = match prev_end {
+            Some(end) if c.begin > end && c.begin - end > min => {
+                Some(Cue::new(None, end, c.begin, vec![String::from("[gap]")]))
+            }
+            _ => None,
+        };
+        prev_end = Some(prev_end.map_or(c.end, |end| end.max(c.end)));
+        gap
+    })
+}
+#[test]
+fn test_gaps() {
+    let cues = vec![
+        cue(1000, 3000, &["Hello"]),
+        cue(10000, 12000, &["World"]),
+        cue(13000, 14000, &["!"]),
+    ];
+    assert_eq!(
+        gaps(cues.into_iter(), Duration::new(5, 0)).collect::<Vec<Cue>>(),
+        vec![cue(3000, 10000, &["[gap]"])]
+    );
+}
+
+/// Remove the HTML tags (`<i>`, `<font color="red">`...) and the ASS override
+/// tags (`{\an8}`...) from the cue text. The lines empty after it are removed.
+pub fn strip_tags<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    iter.map(|mut c| {
+        c.text = c
+            .text
+            .iter()
+            .filter_map(|l| {
+                let stripped = strip_line_tags(l);
+                if stripped.trim().is_empty() && !l.trim().is_empty() {
+                    None
+                } else {
+                    Some(stripped)
+                }
+            })
+            .collect();
+        c
+    })
+}
+
+/// Remove the tags from one line.
+fn strip_line_tags(l: &str) -> String {
+    map_tags(l, true, |_| String::new())
+}
+#[test]
+fn test_strip_tags() {
+    assert_eq!(strip_line_tags("<i>Hello</i> <b>World</b>"), "Hello World");
+    assert_eq!(
+        strip_line_tags("<font color=\"#ff0000\"><b>Red</b></font>"),
+        "Red"
+    );
+    assert_eq!(strip_line_tags("{\\an8}{\\i1}Top"), "Top");
+    assert_eq!(strip_line_tags("1 < 2 and 3 > 2"), "1 < 2 and 3 > 2");
+    assert_eq!(strip_line_tags("<<i>>"), "<>");
+    assert_eq!(strip_line_tags("<i>unterminated <b"), "unterminated <b");
+    assert_eq!(strip_line_tags("{unterminated"), "{unterminated");
+
+    let c = cue(1000, 2000, &["<i></i>", "", "<i>Hello</i>"]);
+    let c = strip_tags(std::iter::once(c)).next().unwrap();
+    assert_eq!(c.text, vec![String::from(""), String::from("Hello")]);
+}
+
+/// Split the cues into the consecutive segments of length window, from zero
+/// to the last cue end. Each segment has the cues which intersect it, clamped
+/// into the segment (see `window`), with the absolute times. A segment can be
+/// empty. All the cues are keeped in memory. If window is zero, there is one
+/// segment with all the cues.
+pub fn split<I: Iterator<Item = Cue>>(iter: I, window: Duration) -> impl Iterator<Item = Vec<Cue>> {
+    let cues: Vec<Cue> = iter.collect();
+    let last = cues.iter().map(|c| c.end).max().unwrap_or_default();
+    let nb: u128 = match window.as_nanos() {
+        0 => 1,
+        w => last.as_nanos().div_ceil(w).max(1),
+    };
+    let mut from = Duration::ZERO;
+    (0..nb).map(move |_| match window.is_zero() {
+        true => cues.clone(),
+        false => {
+            let to = from.saturating_add(window);
+            let segment = self::window(cues.iter().cloned(), from, Some(to)).collect();
+            from = to;
+            segment
+        }
+    })
+}
+#[test]
+fn test_split() {
+    let cues = vec![
+        cue(1000, 4000, &[]),
+        cue(6000, 12000, &[]),
+        cue(13000, 15000, &[]),
+        cue(17000, 20000, &[]),
+    ];
+    let segments: Vec<Vec<Cue>> = split(cues.into_iter(), Duration::new(10, 0)).collect();
+    assert_eq!(
+        segments,
+        vec![
+            vec![cue(1000, 4000, &[]), cue(6000, 10000, &[])],
+            vec![
+                cue(10000, 12000, &[]),
+                cue(13000, 15000, &[]),
+                cue(17000, 20000, &[])
+            ],
+        ]
+    );
+    assert_eq!(
+        split(Vec::new().into_iter(), Duration::new(10, 0)).count(),
+        1
+    );
+
+    // More segments than u32::MAX.
+    let far = cue(4_999_999_999, 5_000_000_000, &[]);
+    let mut segments = split(std::iter::once(far), Duration::from_millis(1));
+    assert_eq!(segments.size_hint(), (5_000_000_000, Some(5_000_000_000)));
+    assert_eq!(segments.next(), Some(Vec::new()));
+}
+
+/// Keep the cues which intersect the window from `from` to `to` (or the end),
+/// their begin and end are clamped into the window.
+pub fn window<I: Iterator<Item = Cue>>(
+    iter: I,
+    from: Duration,
+    to: Option<Duration>,
+) -> impl Iterator<Item = Cue> {
+    iter.filter_map(move |mut c| {
+        if c.end <= from || to.is_some_and(|to| c.begin >= to) {
+            return None;
+        }
+        c.begin = c.begin.max(from);
+        if let Some(to) = to {
+            c.end = c.end.min(to);
+        }
+        Some(c)
+    })
+}
+#[test]
+fn test_window() {
+    let cues = vec![
+        cue(1000, 5000, &[]),
+        cue(8000, 12000, &[]),
+        cue(15000, 18000, &[]),
+        cue(19000, 25000, &[]),
+        cue(30000, 32000, &[]),
+    ];
+    let kept: Vec<Cue> = window(
+        cues.into_iter(),
+        Duration::new(10, 0),
+        Some(Duration::new(20, 0)),
+    )
+    .collect();
+    assert_eq!(
+        kept,
+        vec![
+            cue(10000, 12000, &[]),
+            cue(15000, 18000, &[]),
+            cue(19000, 20000, &[])
+        ]
+    );
+
+    let kept: Vec<Cue> = window(
+        vec![cue(8000, 12000, &[])].into_iter(),
+        Duration::new(10, 0),
+        None,
+    )
+    .collect();
+    assert_eq!(kept, vec![cue(10000, 12000, &[])]);
+}
+
+/// Sort the cues by begin and then by end. The sort is stable, so the cues
+/// with the same times keep their order. All the cues are buffered in memory
+/// before the first one is returned.
+pub fn sort_cues<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    let mut cues: Vec<Cue> = iter.collect();
+    cues.sort_by(|a, b| a.begin.cmp(&b.begin).then(a.end.cmp(&b.end)));
+    cues.into_iter()
+}
+#[test]
+fn test_sort_cues() {
+    let cues = vec![
+        cue(5000, 6000, &["c"]),
+        cue(1000, 3000, &["b"]),
+        cue(1000, 2000, &["a"]),
+        cue(5000, 6000, &["d"]),
+    ];
+    let sorted: Vec<Cue> = sort_cues(cues.into_iter()).collect();
+    assert_eq!(
+        sorted,
+        vec![
+            cue(1000, 2000, &["a"]),
+            cue(1000, 3000, &["b"]),
+            cue(5000, 6000, &["c"]),
+            cue(5000, 6000, &["d"])
+        ]
+    );
+}
+
+/// Wrap the lines longer than max characters on the word boundaries. A word
+/// longer than max is keeped alone on its line. The trailing spaces are
+/// removed.
+pub fn wrap_lines<I: Iterator<Item = Cue>>(iter: I, max: usize) -> impl Iterator<Item = Cue> {
+    iter.map(move |mut c| {
+        c.text = c.text.iter().flat_map(|l| wrap_line(l, max)).collect();
+        c
+    })
+}
+
+/// Wrap one line.
+fn wrap_line(l: &str, max: usize) -> Vec<String> {
+    let l = l.trim_end();
+    if l.chars().count() <= max {
+        return vec![l.to_string()];
+    }
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    let mut len = 0;
+    for word in l.split_whitespace() {
+        let word_len = word.chars().count();
+        if len > 0 && len + 1 + word_len > max {
+            lines.push(std::mem::take(&mut current));
+            len = 0;
+        }
+        if len > 0 {
+            current.push(' ');
+            len += 1;
+        }
+        current.push_str(word);
+        len += word_len;
+    }
+    if len > 0 {
+        lines.push(current);
+    }
+    lines
+}
+#[test]
+fn test_wrap_lines() {
+    assert_eq!(wrap_line("Short line  ", 42), vec!["Short line"]);
+    assert_eq!(
+        wrap_line(
+            "Never drink liquid nitrogen, it will perforate your stomach.",
+            20
+        ),
+        vec![
+            "Never drink liquid",
+            "nitrogen, it will",
+            "perforate your",
+            "stomach."
+        ]
+    );
+    assert_eq!(
+        wrap_line("A supercalifragilistic word", 10),
+        vec!["A", "supercalifragilistic", "word"]
+    );
+
+    let c = cue(1000, 2000, &["Hello World", "Yo"]);
+    let c = wrap_lines(std::iter::once(c), 6).next().unwrap();
+    assert_eq!(c.text, vec!["Hello", "World", "Yo"]);
+}
+
+/// Join the lines of each cue into one line, separated by sep. A cue without
+/// line is keeped without line.
+pub fn join_lines<I: Iterator<Item = Cue>>(iter: I, sep: &str) -> impl Iterator<Item = Cue> {
+    let sep = sep.to_string();
+    iter.map(move |mut c| {
+        if !c.text.is_empty() {
+            c.text = vec![c.text.join(&sep)];
+        }
+        c
+    })
+}
+#[test]
+fn test_join_lines() {
+    let c = cue(1000, 2000, &["Never drink", "liquid nitrogen."]);
+    let c = join_lines(std::iter::once(c), " / ").next().unwrap();
+    assert_eq!(c.text, vec!["Never drink / liquid nitrogen."]);
+}
+
+/// The cleanups of `normalize_typography`, all enabled by default.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Typography {
+    /// Replace the leading dialogue dash (`-`, `--`, `–` or `—`) by an em
+    /// dash and one space.
+    pub dashes: bool,
+    /// Replace the straight quotes by the curly quotes, outside the tags.
+    pub quotes: bool,
+    /// Collapse the runs of spaces into one space.
+    pub spaces: bool,
+}
+impl Default for Typography {
+    fn default() -> Self {
+        Typography {
+            dashes: true,
+            quotes: true,
+            spaces: true,
+        }
+    }
+}
+
+/// Clean up the typography of the cue lines, see `Typography`.
+pub fn normalize_typography<I: Iterator<Item = Cue>>(
+    iter: I,
+    typography: Typography,
+) -> impl Iterator<Item = Cue> {
+    iter.map(move |mut c| {
+        for l in c.text.iter_mut() {
+            if typography.spaces {
+                *l = collapse_spaces(l);
+            }
+            if typography.dashes {
+                *l = normalize_dash(l);
+            }
+            if typography.quotes {
+                *l = curly_quotes(l);
+            }
+        }
+        c
+    })
+}
+#[test]
+fn test_normalize_typography() {
+    let input = cue(
+        1000,
+        2000,
+        &[
+            "--Never  drink \"liquid\" nitrogen.",
+            "–  It's <font color=\"red\">cold</font>.",
+            "-5 degrees",
+        ],
+    );
+
+    let out: Vec<Cue> =
+        normalize_typography(vec![input.clone()].into_iter(), Typography::default()).collect();
+    assert_eq!(
+        out,
+        vec![cue(
+            1000,
+            2000,
+            &[
+                "— Never drink “liquid” nitrogen.",
+                "— It’s <font color=\"red\">cold</font>.",
+                "-5 degrees",
+            ]
+        )]
+    );
+
+    let typography = Typography {
+        quotes: false,
+        ..Typography::default()
+    };
+    let out: Vec<Cue> = normalize_typography(vec![input].into_iter(), typography).collect();
+    assert_eq!(
+        out,
+        vec![cue(
+            1000,
+            2000,
+            &[
+                "— Never drink \"liquid\" nitrogen.",
+                "— It's <font color=\"red\">cold</font>.",
+                "-5 degrees",
+            ]
+        )]
+    );
+
+    // A lone `<` is a text, the next quotes are curled.
+    let out: Vec<Cue> = normalize_typography(
+        std::iter::once(cue(1000, 2000, &["1 < 2 is \"true\" <i>'yes'</i>"])),
+        Typography::default(),
+    )
+    .collect();
+    assert_eq!(
+        out,
+        vec![cue(1000, 2000, &["1 < 2 is “true” <i>‘yes’</i>"])]
+    );
+}
+
+/// Collapse the runs of spaces into one space.
+fn collapse_spaces(l: &str) -> String {
+    let mut out = String::with_capacity(l.len());
+    for c in l.chars() {
+        if c != ' ' || !out.ends_with(' ') {
+            out.push(c);
+        }
+    }
+    out
+}
+
+/// Replace the leading dialogue dash by an em dash and one space. A dash
+/// before a digit, like `-5`, is keeped.
+fn normalize_dash(l: &str) -> String {
+    let rest = ["--", "-", "–", "—"]
+        .iter()
+        .find_map(|dash| l.strip_prefix(dash))
+        .filter(|rest| !rest.starts_with(|c: char| c.is_ascii_digit()));
+    match rest {
+        Some(rest) => format!("— {}", rest.trim_start()),
+        None => l.to_string(),
+    }
+}
+
+/// Replace the straight quotes by the curly quotes, the quote is opening
+/// at the line begin or after a space, an opening bracket or a dash. The
+/// quotes in the tags `<...>` are keeped.
+fn curly_quotes(l: &str) -> String {
+    let mut prev: Option<char> = None;
+    map_text(l, false, |t| {
+        let mut out = String::with_capacity(t.len());
+        for c in t.chars() {
+            let opening = prev.is_none_or(|p| p.is_whitespace() || "([{—–-".contains(p));
+            match c {
+                '"' if opening => out.push('“'),
+                '"' => out.push('”'),
+                '\'' if opening => out.push('‘'),
+                '\'' => out.push('’'),
+                c => out.push(c),
+            }
+            prev = Some(c);
+        }
+        out
+    })
+}
+
+/// The letter case of `transform_case`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Case {
+    /// All the letters in upper case.
+    Upper,
+    /// All the letters in lower case.
+    Lower,
+    /// The first letter of the cue and of each sentence in upper case, the
+    /// others letters in lower case.
+    Sentence,
+}
+
+/// Change the case of the letters of the cue text, the tags are keeped.
+pub fn transform_case<I: Iterator<Item = Cue>>(iter: I, case: Case) -> impl Iterator<Item = Cue> {
+    iter.map(move |mut c| {
+        let mut capitalize = true;
+        for l in c.text.iter_mut() {
+            *l = change_case(l, case, &mut capitalize);
+        }
+        c
+    })
+}
+#[test]
+fn test_transform_case() {
+    let apply = |case: Case, text: &[&str]| {
+        transform_case(std::iter::once(cue(1000, 2000, text)), case).next()
+    };
+
+    let text = ["<i>Never</i> drink liquid", "NITROGEN. it will ÉCLATER!"];
+    assert_eq!(
+        apply(Case::Upper, &text),
+        Some(cue(
+            1000,
+            2000,
+            &["<i>NEVER</i> DRINK LIQUID", "NITROGEN. IT WILL ÉCLATER!"]
+        ))
+    );
+    assert_eq!(
+        apply(Case::Lower, &text),
+        Some(cue(
+            1000,
+            2000,
+            &["<i>never</i> drink liquid", "nitrogen. it will éclater!"]
+        ))
+    );
+    assert_eq!(
+        apply(Case::Sentence, &text),
+        Some(cue(
+            1000,
+            2000,
+            &["<i>Never</i> drink liquid", "nitrogen. It will éclater!"]
+        ))
+    );
+    assert_eq!(
+        apply(Case::Sentence, &["<v Bob>élise? OUI.", "— ET TOI."]),
+        Some(cue(1000, 2000, &["<v Bob>Élise? Oui.", "— Et toi."]))
+    );
+    assert_eq!(
+        apply(Case::Upper, &["straße"]),
+        Some(cue(1000, 2000, &["STRASSE"]))
+    );
+    assert_eq!(
+        apply(Case::Upper, &["1 < 2 and <i>more</i>"]),
+        Some(cue(1000, 2000, &["1 < 2 AND <i>MORE</i>"]))
+    );
+    assert_eq!(
+        apply(Case::Sentence, &["a < b. so <b"]),
+        Some(cue(1000, 2000, &["A < b. So <b"]))
+    );
+}
+
+/// Change the case of one line, outside the tags. With the sentence case,
+/// capitalize is true if the next letter begins a sentence.
+fn change_case(l: &str, case: Case, capitalize: &mut bool) -> String {
+    map_text(l, false, |t| match case {
+        Case::Upper => t.to_uppercase(),
+        Case::Lower => t.to_lowercase(),
+        Case::Sentence => {
+            let mut out = String::with_capacity(t.len());
+            for c in t.chars() {
+                if c.is_alphabetic() && *capitalize {
+                    *capitalize = false;
+                    out.extend(c.to_uppercase());
+                } else {
+                    if ".!?".contains(c) {
+                        *capitalize = true;
+                    }
+                    out.extend(c.to_lowercase());
+                }
+            }
+            out
+        }
+    })
+}
+
+/// Keep the cues with at least one line matched by the predicate.
+pub fn filter_text<I, P>(iter: I, mut predicate: P) -> impl Iterator<Item = Cue>
+where
+    I: Iterator<Item = Cue>,
+    P: FnMut(&str) -> bool,
+{
+    iter.filter(move |c| c.text.iter().any(|l| predicate(l)))
+}
+#[test]
+fn test_filter_text() {
+    let cues = vec![
+        cue(1000, 2000, &["Never drink", "liquid Nitrogen."]),
+        cue(1000, 2000, &["It will perforate your stomach."]),
+        cue(1000, 2000, &["Nitrogen is cold."]),
+    ];
+    let kept: Vec<Cue> =
+        filter_text(cues.into_iter(), |l| l.to_lowercase().contains("nitrogen")).collect();
+    assert_eq!(
+        kept,
+        vec![
+            cue(1000, 2000, &["Never drink", "liquid Nitrogen."]),
+            cue(1000, 2000, &["Nitrogen is cold."])
+        ]
+    );
+}
+
+/// Replace all the occurrences of from by to in the cue lines. A line empty
+/// after it is keeped.
+pub fn replace_text<I: Iterator<Item = Cue>>(
+    iter: I,
+    from: &str,
+    to: &str,
+) -> impl Iterator<Item = Cue> {
+    let from = from.to_string();
+    let to = to.to_string();
+    iter.map(move |mut c| {
+        for l in c.text.iter_mut() {
+            *l = l.replace(&from, &to);
+        }
+        c
+    })
+}
+#[test]
+fn test_replace_text() {
+    let c = cue(1000, 2000, &["Hello Wrold", "Wrold"]);
+    let c = replace_text(std::iter::once(c), "Wrold", "World")
+        .next()
+        .unwrap();
+    assert_eq!(c.text, vec!["Hello World", "World"]);
+    let c = replace_text(std::iter::once(c), "World", "")
+        .next()
+        .unwrap();
+    assert_eq!(c.text, vec!["Hello ", ""]);
+}
+
+/// Replace the matches of the regex by to, which can use the capture groups
+/// like `$1`.
+#[cfg(feature = "regex")]
+pub fn replace_regex<I: Iterator<Item = Cue>>(
+    iter: I,
+    re: &regex::Regex,
+    to: &str,
+) -> impl Iterator<Item = Cue> {
+    let re = re.clone();
+    let to = to.to_string();
+    iter.map(move |mut c| {
+        for l in c.text.iter_mut() {
+            *l = re.replace_all(l, to.as_str()).into_owned();
+        }
+        c
+    })
+}
+#[cfg(feature = "regex")]
+#[test]
+fn test_replace_regex() {
+    let c = cue(1000, 2000, &["Mr Smith and Mr Jones"]);
+    let re = regex::Regex::new(r"Mr (\w+)").unwrap();
+    let c = replace_regex(std::iter::once(c), &re, "$1").next().unwrap();
+    assert_eq!(c.text, vec!["Smith and Jones"]);
+}
+
+/// Remove the empty or whitespace only lines of the cues.
+pub fn strip_empty_lines<I: Iterator<Item = Cue>>(iter: I) -> impl Iterator<Item = Cue> {
+    iter.map(|mut c| {
+        c.text.retain(|l| !l.trim().is_empty());
+        c
+    })
+}
+
+/// Apply the delta only on the cues which begin at or after the pivot.
+pub fn delta_after<I: Iterator<Item = Cue>>(
+    iter: I,
+    pivot: Duration,
+    delta: Delta,
+) -> impl Iterator<Item = Cue> {
+    iter.map(move |mut c| {
+        if c.begin >= pivot {
+            delta.apply(&mut c);
+        }
+        c
+    })
+}
+#[test]
+fn test_delta_after() {
+    let cues = vec![
+        cue(10000, 12000, &[]),
+        cue(2699000, 2701000, &[]),
+        cue(2700000, 2702000, &[]),
+    ];
+    let shifted: Vec<Cue> = delta_after(
+        cues.into_iter(),
+        Duration::new(2700, 0),
+        Delta::Add(Duration::new(12, 0)),
+    )
+    .collect();
+    assert_eq!(
+        shifted,
+        vec![
+            cue(10000, 12000, &[]),
+            cue(2699000, 2701000, &[]),
+            cue(2712000, 2714000, &[])
+        ]
+    );
+}
+
+/// Shift all the cues so the last end is at target. Return the applied delta
+/// and the shifted cues, all the cues are keeped in memory. Without cue, the
+/// delta is `Delta::None`.
+pub fn delta_to_end<I: Iterator<Item = Cue>>(iter: I, target: Duration) -> (Delta, Vec<Cue>) {
+    let mut cues: Vec<Cue> = iter.collect();
+    let delta = match cues.iter().map(|c| c.end).max() {
+        None => Delta::None,
+        Some(last) if last <= target => Delta::Add(target - last),
+        Some(last) => Delta::Sub(last - target),
+    };
+    for c in cues.iter_mut() {
+        delta.apply(c);
+    }
+    (delta, cues)
+}
+#[test]
+fn test_delta_to_end() {
+    let (delta, cues) = delta_to_end(
+        vec![cue(60000, 65000, &[]), cue(2990000, 3000000, &[])].into_iter(),
+        Duration::new(3600, 0),
+    );
+    assert_eq!(delta, Delta::Add(Duration::new(600, 0)));
+    assert_eq!(
+        cues,
+        vec![cue(660000, 665000, &[]), cue(3590000, 3600000, &[])]
+    );
+
+    let (delta, _) = delta_to_end(
+        vec![cue(60000, 65000, &[])].into_iter(),
+        Duration::new(5, 0),
+    );
+    assert_eq!(delta, Delta::Sub(Duration::new(60, 0)));
+    let (delta, cues) = delta_to_end(std::iter::empty(), Duration::new(5, 0));
+    assert_eq!((delta, cues), (Delta::None, Vec::new()));
+}
+
+/// Shift all the cues so the first cue begins at target. Only the first cue
+/// is peeked, the other cues are not keeped in memory.
+pub fn shift_first_to<I: Iterator<Item = Cue>>(
+    iter: I,
+    target: Duration,
+) -> impl Iterator<Item = Cue> {
+    let mut iter = iter.peekable();
+    let delta = match iter.peek().map(|c| c.begin) {
+        None => Delta::None,
+        Some(first) if first <= target => Delta::Add(target - first),
+        Some(first) => Delta::Sub(first - target),
+    };
+    iter.map(delta.applicator())
+}
+#[test]
+fn test_shift_first_to() {
+    let cues = vec![cue(125000, 127000, &[]), cue(130000, 134000, &[])];
+    let shifted: Vec<Cue> = shift_first_to(cues.into_iter(), Duration::ZERO).collect();
+    assert_eq!(shifted, vec![cue(0, 2000, &[]), cue(5000, 9000, &[])]);
+
+    let shifted: Vec<Cue> =
+        shift_first_to(vec![cue(5000, 6000, &[])].into_iter(), Duration::new(60, 0)).collect();
+    assert_eq!(shifted, vec![cue(60000, 61000, &[])]);
+}
+
+/// Round the begin and the end of the cues to the nearest multiple of step.
+pub fn round_times<I: Iterator<Item = Cue>>(iter: I, step: Duration) -> impl Iterator<Item = Cue> {
+    snap_times(iter, step, false)
+}
+
+/// Like `round_times`, but round down to the multiple of step.
+pub fn truncate_times<I: Iterator<Item = Cue>>(
+    iter: I,
+    step: Duration,
+) -> impl Iterator<Item = Cue> {
+    snap_times(iter, step, true)
+}
+
+/// Snap the times to a multiple of step, down if floor or else to the nearest.
+fn snap_times<I: Iterator<Item = Cue>>(
+    iter: I,
+    step: Duration,
+    floor: bool,
+) -> impl Iterator<Item = Cue> {
+    let step = step.as_nanos();
+    let snap = move |d: Duration| -> Duration {
+        if step == 0 {
+            return d;
+        }
+        let n = d.as_nanos() + if floor { 0 } else { step / 2 };
+        let n = n / step * step;
+        Duration::new((n / 1_000_000_000) as u64, (n % 1_000_000_000) as u32)
+    };
+    iter.map(move |mut c| {
+        c.begin = snap(c.begin);
+        c.end = snap(c.end).max(c.begin);
+        c
+    })
+}
+#[test]
+fn test_round_times() {
+    let round = |step: u64| -> Vec<Cue> {
+        round_times(
+            vec![cue(1234, 2750, &[])].into_iter(),
+            Duration::from_millis(step),
+        )
+        .collect()
+    };
+    assert_eq!(round(100), vec![cue(1200, 2800, &[])]);
+    assert_eq!(round(1000), vec![cue(1000, 3000, &[])]);
+
+    let truncated: Vec<Cue> = truncate_times(
+        vec![cue(1234, 2750, &[])].into_iter(),
+        Duration::from_secs(1),
+    )
+    .collect();
+    assert_eq!(truncated, vec![cue(1000, 2000, &[])]);
+}
+
+/// Round the begin and the end of the cues to the nearest frame at the
+/// framerate, like `25.0` or `29.97`. An invalid framerate keeps the times.
+pub fn round_to_frames<I: Iterator<Item = Cue>>(iter: I, fps: f64) -> impl Iterator<Item = Cue> {
+    let snap = move |d: Duration| -> Duration {
+        if !fps.is_finite() || fps <= 0.0 {
+            return d;
+        }
+        Duration::from_secs_f64((d.as_secs_f64() * fps).round() / fps)
+    };
+    iter.map(move |mut c| {
+        c.begin = snap(c.begin);
+        c.end = snap(c.end).max(c.begin);
+        c
+    })
+}
+#[test]
+fn test_round_to_frames() {
+    let round = |begin: u64, end: u64, fps: f64| -> (Duration, Duration) {
+        let c = cue(begin, end, &[]);
+        let c = round_to_frames(std::iter::once(c), fps).next().unwrap();
+        (c.begin, c.end)
+    };
+
+    assert_eq!(
+        round(1019, 1021, 25.0),
+        (Duration::from_millis(1000), Duration::from_millis(1040))
+    );
+    assert_eq!(
+        round(1000, 2000, 29.97),
+        (
+            Duration::from_secs_f64(30.0 / 29.97),
+            Duration::from_secs_f64(60.0 / 29.97)
+        )
+    );
+    assert_eq!(round(1000, 2000, 29.97).0.as_micros(), 1_001_001);
+    assert_eq!(
+        round(1019, 1021, 0.0),
+        (Duration::from_millis(1019), Duration::from_millis(1021))
+    );
+}
+
+/// Split the cues with more than n lines into sequential cues of n lines. The
+/// time is divided proportionally to the number of characters, with min_gap
+/// between the new cues; the last one ends at the end of the original cue.
+pub fn max_lines<I: Iterator<Item = Cue>>(
+    iter: I,
+    n: usize,
+    min_gap: Duration,
+) -> impl Iterator<Item = Cue> {
+    iter.flat_map(move |c| split_lines(c, n.max(1), min_gap))
+}
+
+/// Split one cue into cues of n lines.
+fn split_lines(c: Cue, n: usize, min_gap: Duration) -> Vec<Cue> {
+    if c.text.len() <= n {
+        return vec![c];
+    }
+
+    let chunks: Vec<Vec<String>> = c.text.chunks(n).map(|l| l.to_vec()).collect();
+    let weights: Vec<usize> = chunks
+        .iter()
+        .map(|l| l.iter().map(|l| l.chars().count()).sum::<usize>().max(1))
+        .collect();
+    let total: usize = weights.iter().sum();
+    let gaps = min_gap * (chunks.len() as u32 - 1);
+    let available = c.duration().saturating_sub(gaps);
+
+    let mut begin = c.begin;
+    let last = chunks.len() - 1;
+    chunks
+        .into_iter()
+        .zip(weights)
+        .enumerate()
+        .map(|(i, (text, weight))| {
+            let end = match i == last {
+                true => c.end,
+                false => (begin + available.mul_f64(weight as f64 / total as f64)).min(c.end),
+            };
+            let cue = Cue {
+                id: if i == 0 { c.id.clone() } else { None },
+                begin,
+                end,
+                text,
+                settings: c.settings.clone(),
+                coordinates: c.coordinates.clone(),
+            };
+            begin = (end + min_gap).min(c.end);
+            cue
+        })
+        .collect()
+}
+#[test]
+fn test_max_lines() {
+    let four = cue(0, 4000, &["Line 1", "Line 2", "Line 3", "Line 4"]);
+    let split: Vec<Cue> = max_lines(vec![four.clone()].into_iter(), 2, Duration::ZERO).collect();
+    assert_eq!(
+        split,
+        vec![
+            cue(0, 2000, &["Line 1", "Line 2"]),
+            cue(2000, 4000, &["Line 3", "Line 4"]),
+        ]
+    );
+
+    let split: Vec<Cue> =
+        max_lines(vec![four].into_iter(), 2, Duration::from_millis(100)).collect();
+    assert_eq!(
+        split,
+        vec![
+            cue(0, 1950, &["Line 1", "Line 2"]),
+            cue(2050, 4000, &["Line 3", "Line 4"]),
+        ]
+    );
+
+    let two = cue(0, 1000, &["Short", "Lines"]);
+    let kept: Vec<Cue> = max_lines(vec![two.clone()].into_iter(), 2, Duration::ZERO).collect();
+    assert_eq!(kept, vec![two]);
+}
+
+/// Keep the last n cues. The n last cues are keeped in memory.
+pub fn tail<I: Iterator<Item = Cue>>(iter: I, n: usize) -> impl Iterator<Item = Cue> {
+    let mut last = std::collections::VecDeque::with_capacity(n);
+    for c in iter {
+        if last.len() == n {
+            last.pop_front();
+        }
+        if n > 0 {
+            last.push_back(c);
+        }
+    }
+    last.into_iter()
+}
+#[test]
+fn test_tail() {
+    let cues = || {
+        (1..=5).map(|i| {
+            Cue::new(
+                None,
+                Duration::new(i, 0),
+                Duration::new(i + 1, 0),
+                vec![format!("Cue {}", i)],
+            )
+        })
+    };
+    let begins = |cues: Vec<Cue>| cues.iter().map(|c| c.begin.as_secs()).collect::<Vec<_>>();
+
+    assert_eq!(begins(cues().take(2).collect()), vec![1, 2]);
+    assert_eq!(begins(tail(cues(), 2).collect()), vec![4, 5]);
+    assert_eq!(begins(tail(cues(), 10).collect()), vec![1, 2, 3, 4, 5]);
+    assert_eq!(tail(cues(), 0).count(), 0);
+
+    // The SRT output is numbered from 1.
+    let mut output: Vec<u8> = Vec::new();
+    super::srt_out(tail(cues(), 1), &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "1\n00:00:05,000 --> 00:00:06,000\nCue 5\n\n"
+    );
+}
@@ -0,0 +1,250 @@
+// Copyright (c) 2020, Hugues GUILLEUS <ghugues@netc.fr>. All rights reserved.
+// Use of this source code is governed by a BSD
+// license that can be found in the LICENSE file.
+
+use super::tags::map_tags;
+use super::{Cue, WebVTTTimestamp};
+use std::fmt;
+use std::time::Duration;
+
+/// Create a cue for the tests, begin and end are in milliseconds.
+#[cfg(test)]
+fn cue(begin: u64, end: u64, text: &[&str]) -> Cue {
+    Cue::new(
+        None,
+        Duration::from_millis(begin),
+        Duration::from_millis(end),
+        text.iter().map(|t| t.to_string()).collect(),
+    )
+}
+
+/// A problem found by `validate`. The index is the number of the cue, from 1
+/// like in SRT.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Warning {
+    /// The cue begins before the end of the previous cue.
+    Overlap {
+        index: usize,
+        prev_end: Duration,
+        next_begin: Duration,
+    },
+    /// The cue ends before its begin.
+    NegativeDuration { index: usize },
+    /// The cue ends at its begin.
+    ZeroDuration { index: usize },
+    /// The cue begins before the previous cue.
+    OutOfOrder { index: usize },
+    /// The cue is too short to keep the gap before the next cue, see
+    /// `enforce_gap_with`.
+    GapTooShort { index: usize },
+}
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Warning::Overlap {
+                index,
+                prev_end,
+                next_begin,
+            } => write!(
+                f,
+                "cue {} begins at {:?} before the end of the previous cue at {:?}",
+                index, next_begin, prev_end
+            ),
+            Warning::NegativeDuration { index } => write!(f, "cue {} ends before its begin", index),
+            Warning::ZeroDuration { index } => write!(f, "cue {} has a zero duration", index),
+            Warning::OutOfOrder { index } => {
+                write!(f, "cue {} begins before the previous cue", index)
+            }
+            Warning::GapTooShort { index } => write!(
+                f,
+                "cue {} is too short to keep the gap before the next cue",
+                index
+            ),
+        }
+    }
+}
+
+/// Check the cues and return the found problems, the cues are not modified.
+pub fn validate<I: Iterator<Item = Cue>>(iter: I) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    let mut prev: Option<Cue> = None;
+
+    for (i, c) in iter.enumerate() {
+        let index = i + 1;
+        if c.end < c.begin {
+            warnings.push(Warning::NegativeDuration { index });
+        } else if c.end == c.begin {
+            warnings.push(Warning::ZeroDuration { index });
+        }
+        if let Some(prev) = &prev {
+            if c.begin < prev.begin {
+                warnings.push(Warning::OutOfOrder { index });
+            } else if c.begin < prev.end {
+                warnings.push(Warning::Overlap {
+                    index,
+                    prev_end: prev.end,
+                    next_begin: c.begin,
+                });
+            }
+        }
+        prev = Some(c);
+    }
+
+    warnings
+}
+#[test]
+fn test_validate() {
+    // Cue::new swaps the times, so build the negative cue by hand.
+    let mut negative = cue(8000, 9000, &[]);
+    negative.begin = Duration::new(9, 0);
+    negative.end = Duration::new(8, 0);
+
+    let cues = vec![
+        cue(1000, 4000, &[]),
+        cue(3000, 5000, &[]),
+        cue(6000, 6000, &[]),
+        cue(2000, 3000, &[]),
+        negative,
+    ];
+    assert_eq!(
+        validate(cues.into_iter()),
+        vec![
+            Warning::Overlap {
+                index: 2,
+                prev_end: Duration::new(4, 0),
+                next_begin: Duration::new(3, 0),
+            },
+            Warning::ZeroDuration { index: 3 },
+            Warning::OutOfOrder { index: 4 },
+            Warning::NegativeDuration { index: 5 },
+        ]
+    );
+    assert!(validate(vec![cue(1000, 2000, &[]), cue(2000, 3000, &[])].into_iter()).is_empty());
+}
+
+/// The reading speed of the cue, in characters per second. The tags are not
+/// counted. A cue with text and a zero duration has an infinite speed.
+pub fn cps(cue: &Cue) -> f64 {
+    let chars = chars(cue);
+    if chars == 0 {
+        0.0
+    } else {
+        chars as f64 / cue.duration().as_secs_f64()
+    }
+}
+#[test]
+fn test_cps() {
+    let mut c = cue(1000, 3000, &["<i>Hello</i>", "World"]);
+    assert_eq!(cps(&c), 5.0);
+    c.end = c.begin;
+    assert_eq!(cps(&c), f64::INFINITY);
+    c.text.clear();
+    assert_eq!(cps(&c), 0.0);
+}
+
+/// The begin or the end of the cue has a fraction of millisecond, truncated by
+/// the millisecond formats like SRT and WebVTT.
+pub fn has_sub_millis(cue: &Cue) -> bool {
+    [cue.begin, cue.end]
+        .iter()
+        .any(|d| !d.subsec_nanos().is_multiple_of(1_000_000))
+}
+#[test]
+fn test_has_sub_millis() {
+    let cue = |begin: u64, end: u64| {
+        Cue::new(
+            None,
+            Duration::from_micros(begin),
+            Duration::from_micros(end),
+            vec![String::from("Hello")],
+        )
+    };
+    let cues = [
+        cue(1_000_000, 2_000_000),
+        cue(3_000_500, 4_000_000),
+        cue(5_000_000, 6_000_001),
+        cue(7_001_000, 8_999_000),
+    ];
+    assert_eq!(cues.iter().filter(|c| has_sub_millis(c)).count(), 2);
+}
+
+/// The number of characters of the cue text, without the tags.
+fn chars(cue: &Cue) -> usize {
+    cue.text
+        .iter()
+        .map(|l| map_tags(l, true, |_| String::new()).chars().count())
+        .sum()
+}
+
+/// A summary of the cues, see `stats`.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    /// The number of cues.
+    pub cues: usize,
+    /// The sum of the cue durations.
+    pub duration: Duration,
+    /// The first begin, none if there is no cue.
+    pub begin: Option<Duration>,
+    /// The last end, none if there is no cue.
+    pub end: Option<Duration>,
+    /// The average reading speed: all the characters (without the tags) per
+    /// the total duration. Zero if the total duration is zero.
+    pub cps: f64,
+}
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let time =
+            |d: Option<Duration>| d.map_or(String::from("-"), |d| WebVTTTimestamp(d).to_string());
+        writeln!(f, "cues: {}", self.cues)?;
+        writeln!(f, "duration: {}", WebVTTTimestamp(self.duration))?;
+        writeln!(f, "begin: {}", time(self.begin))?;
+        writeln!(f, "end: {}", time(self.end))?;
+        write!(f, "average cps: {:.1}", self.cps)
+    }
+}
+
+/// Count the cues, their duration, the first begin, the last end and the
+/// average reading speed.
+pub fn stats<I: Iterator<Item = Cue>>(iter: I) -> Stats {
+    let mut s = Stats::default();
+    let mut chars = 0;
+    for c in iter {
+        s.cues += 1;
+        s.duration += c.duration();
+        s.begin = Some(s.begin.map_or(c.begin, |b| b.min(c.begin)));
+        s.end = Some(s.end.map_or(c.end, |e| e.max(c.end)));
+        chars += self::chars(&c);
+    }
+    if !s.duration.is_zero() {
+        s.cps = chars as f64 / s.duration.as_secs_f64();
+    }
+    s
+}
+#[test]
+fn test_stats() {
+    let s = stats(
+        vec![
+            cue(5000, 7000, &["Hello"]),
+            cue(1000, 4000, &["<i>World</i>"]),
+        ]
+        .into_iter(),
+    );
+    assert_eq!(
+        s,
+        Stats {
+            cues: 2,
+            duration: Duration::new(5, 0),
+            begin: Some(Duration::new(1, 0)),
+            end: Some(Duration::new(7, 0)),
+            cps: 2.0,
+        }
+    );
+    assert_eq!(
+        s.to_string(),
+        "cues: 2\nduration: 00:05.000\nbegin: 00:01.000\nend: 00:07.000\naverage cps: 2.0"
+    );
+
+    let s = stats(std::iter::empty());
+    assert_eq!(s, Stats::default());
+    assert_eq!(s.cps, 0.0);
+}
@@ -2,43 +2,205 @@
 // Use of this source code is governed by a BSD
 // license that can be found in the LICENSE file.
 
-use super::{Cue, LineNb};
+use super::{parse_nanos, Cue, Format, IdPolicy, LineNb, ParseError, SubtitleParser, WriteOptions};
+use std::fmt;
 use std::io;
-use std::io::{ErrorKind, Read, Write};
+use std::io::{Read, Write};
 use std::time::Duration;
 
+/// Create a cue for the tests, begin and end are in milliseconds.
+#[cfg(test)]
+fn cue(id: Option<&str>, begin: u64, end: u64, text: &[&str]) -> Cue {
+    Cue::new(
+        id.map(String::from),
+        Duration::from_millis(begin),
+        Duration::from_millis(end),
+        text.iter().map(|t| t.to_string()).collect(),
+    )
+}
+
+/// How the WebVTT parser handles a blank line in the text of a cue.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub enum BlankLinePolicy {
+    /// The blank line ends the cue, like in the WebVTT specification.
+    #[default]
+    Terminate,
+    /// The blank lines are keeped in the cue text until the next time code
+    /// line or the end of the file. A line just before the time code and
+    /// after a blank line is the id of the next cue. The blank lines at the
+    /// end of the cue are removed.
+    Keep,
+}
+
+/// A REGION definition of the WebVTT header, a cue is placed in it with the
+/// `region:id` setting. The values are keeped as writed, like `40%`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Region {
+    pub id: String,
+    /// The width in percent of the viewport, like `40%`.
+    pub width: Option<String>,
+    /// The number of lines.
+    pub lines: Option<u32>,
+    /// The anchor point of the region, like `0%,100%`.
+    pub region_anchor: Option<String>,
+    /// The anchor point in the viewport, like `10%,90%`.
+    pub viewport_anchor: Option<String>,
+    /// The scroll setting, like `up`.
+    pub scroll: Option<String>,
+}
+impl Region {
+    /// Parse the settings of a REGION block, like `id:fred width:40%`, on one
+    /// or many lines. The unknown settings are ignored.
+    fn parse(lines: &[String]) -> Self {
+        let mut r = Region::default();
+        let settings = lines.iter().flat_map(|l| l.split_whitespace());
+        for (name, value) in settings.filter_map(|s| s.split_once(':')) {
+            let v = || Some(value.to_string());
+            match name {
+                "id" => r.id = value.to_string(),
+                "width" => r.width = v(),
+                "lines" => r.lines = value.parse().ok(),
+                "regionanchor" => r.region_anchor = v(),
+                "viewportanchor" => r.viewport_anchor = v(),
+                "scroll" => r.scroll = v(),
+                _ => {}
+            }
+        }
+        r
+    }
+}
+/// Display the settings of the REGION block, one per line.
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "id:{}", self.id)?;
+        if let Some(width) = &self.width {
+            write!(f, "\nwidth:{}", width)?;
+        }
+        if let Some(lines) = self.lines {
+            write!(f, "\nlines:{}", lines)?;
+        }
+        if let Some(anchor) = &self.region_anchor {
+            write!(f, "\nregionanchor:{}", anchor)?;
+        }
+        if let Some(anchor) = &self.viewport_anchor {
+            write!(f, "\nviewportanchor:{}", anchor)?;
+        }
+        if let Some(scroll) = &self.scroll {
+            write!(f, "\nscroll:{}", scroll)?;
+        }
+        Ok(())
+    }
+}
+
 /// A parser of a WebVTT stream.
 pub struct WebVTTParser<R: Read> {
     lines: LineNb<R>,
     end: bool,
+    /// Accept a comma as milliseconds separator.
+    lenient: bool,
+    /// The HLS `X-TIMESTAMP-MAP` header, the MPEGTS time and the LOCAL time.
+    mpegts: Duration,
+    local: Duration,
+    /// The content of the STYLE blocks before the first cue.
+    styles: Vec<String>,
+    /// The REGION blocks before the first cue.
+    regions: Vec<Region>,
+    /// The lines readed but not yet parsed, the next line is the last.
+    pending: Vec<String>,
+    blank_line_policy: BlankLinePolicy,
+    keep_ids: bool,
 }
 impl<R: Read> WebVTTParser<R> {
     pub fn new(r: R) -> io::Result<Self> {
         let mut lines = LineNb::new(r);
 
         match lines.next() {
-            None => Err(io::Error::new(
-                ErrorKind::UnexpectedEof,
-                "WebVTT file need a `WEBVTT` line header",
-            )),
+            None => Err(ParseError::MissingHeader.into()),
             Some(Err(e)) => Err(e),
-            Some(Ok(l)) if !l.starts_with("WEBVTT") && l.starts_with("\u{EFBB}\u{BF}WEBVTT") => {
-                Err(io::Error::new(
-                    ErrorKind::InvalidData,
-                    "WebVTT file need a `WEBVTT` line header",
-                ))
+            Some(Ok(l)) if !l.trim_start_matches('\u{FEFF}').starts_with("WEBVTT") => {
+                Err(ParseError::MissingHeader.into())
             }
-            _ => Ok(()),
+            Some(Ok(_)) => Ok(()),
         }?;
 
-        Ok(Self {
-            lines: lines,
+        let mut p = Self {
+            lines,
             end: false,
-        })
+            lenient: false,
+            mpegts: Duration::ZERO,
+            local: Duration::ZERO,
+            styles: Vec::new(),
+            regions: Vec::new(),
+            pending: Vec::new(),
+            blank_line_policy: BlankLinePolicy::Terminate,
+            keep_ids: false,
+        };
+        p.read_header().map_err(|e| p.lines.locate(e))?;
+        Ok(p)
+    }
+    /// Read the blocks before the first cue, keep the STYLE and REGION blocks.
+    fn read_header(&mut self) -> io::Result<()> {
+        while let Some(line) = self.lines.next() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            } else if line.starts_with("STYLE") {
+                let style = self.read_block()?;
+                self.styles.push(style.join("\n"));
+            } else if line.starts_with("REGION") {
+                let region = self.read_block()?;
+                self.regions.push(Region::parse(&region));
+            } else if line.starts_with("NOTE") {
+                self.next_while_empty()?;
+            } else if let Some(map) = line.strip_prefix("X-TIMESTAMP-MAP=") {
+                self.parse_timestamp_map(map)?;
+            } else {
+                self.pending.push(line);
+                break;
+            }
+        }
+        Ok(())
+    }
+    /// Read the lines of a block until an empty line.
+    fn read_block(&mut self) -> io::Result<Vec<String>> {
+        let mut block = Vec::new();
+        for l in self.lines.by_ref() {
+            match l? {
+                l if l.is_empty() => break,
+                l => block.push(l),
+            }
+        }
+        Ok(block)
+    }
+    /// The content of the STYLE blocks, like `::cue(b) { color: red; }`.
+    pub fn styles(&self) -> &[String] {
+        &self.styles
+    }
+    /// The REGION definitions of the header.
+    pub fn regions(&self) -> &[Region] {
+        &self.regions
+    }
+    /// In lenient mode, the parser accept a comma as milliseconds separator
+    /// in the time codes, like `00:01,000`. Default to strict.
+    pub fn lenient(mut self, lenient: bool) -> Self {
+        self.lenient = lenient;
+        self
+    }
+    /// Keep the numeric ids, like `1`, as the `Cue.id`. Default to false, so
+    /// a SRT converted to WebVTT and back is renumbered.
+    pub fn keep_ids(mut self, keep: bool) -> Self {
+        self.keep_ids = keep;
+        self
+    }
+    /// Set how a blank line in the text of a cue is handled. Default to
+    /// `BlankLinePolicy::Terminate`.
+    pub fn blank_line_policy(mut self, policy: BlankLinePolicy) -> Self {
+        self.blank_line_policy = policy;
+        self
     }
     /// Try to parse the next cue. If it's the end of the file, return `Ok(None)`.
     fn next_cue(&mut self, id: Option<String>) -> io::Result<Option<Cue>> {
-        let line: String = match self.lines.next() {
+        let line: String = match self.pending.pop().map(Ok).or_else(|| self.lines.next()) {
             None => {
                 return Ok(None);
             }
@@ -53,18 +215,14 @@ impl<R: Read> WebVTTParser<R> {
                 if line.contains("-->") {
                     Ok(Some(self.parse_cue(&line, Some(id))?))
                 } else {
-                    Err(io::Error::new(
-                        ErrorKind::InvalidData,
-                        format!(
-                            "A alone text line {:?} (line: {})",
-                            id,
-                            self.lines.current()
-                        ),
-                    ))
+                    ParseError::unexpected(&id, self.lines.current() - 1)
                 }
             }
             _ => {
-                if line.len() == 0 {
+                if line.trim().is_empty() {
+                    self.next_cue(None)
+                } else if let Some(map) = line.strip_prefix("X-TIMESTAMP-MAP=") {
+                    self.parse_timestamp_map(map)?;
                     self.next_cue(None)
                 } else if line.starts_with("REGION")
                     || line.starts_with("NOTE")
@@ -81,46 +239,103 @@ impl<R: Read> WebVTTParser<R> {
         }
     }
 
-    /// Read lines while the line is not empty and no error come.
+    /// Read lines while the line is not empty and no error come. The end of
+    /// the file is also the end of the block. A line with only spaces is
+    /// empty.
     fn next_while_empty(&mut self) -> io::Result<()> {
-        loop {
-            match self.lines.next() {
-                None => return Ok(()),
-                Some(l) => {
-                    if l?.len() == 0 {
-                        return Ok(());
-                    }
+        for l in self.lines.by_ref() {
+            if l?.trim().is_empty() {
+                break;
+            }
+        }
+        Ok(())
+    }
+    /// Parse the value of the HLS header `X-TIMESTAMP-MAP`, like
+    /// `MPEGTS:900000,LOCAL:00:00:00.000`. The MPEGTS time use a 90kHz clock.
+    fn parse_timestamp_map(&mut self, map: &str) -> io::Result<()> {
+        let line = self.lines.current();
+        for item in map.split(',') {
+            let item = item.trim();
+            if let Some(ts) = item.strip_prefix("MPEGTS:") {
+                match ts.parse::<u64>() {
+                    Ok(ts) => self.mpegts = Duration::from_nanos(ts * 100_000 / 9),
+                    Err(e) => return ParseError::timestamp(e, item, line),
                 }
+            } else if let Some(local) = item.strip_prefix("LOCAL:") {
+                self.local = parse_duration(local, line, self.lenient)?.1;
+            } else {
+                return ParseError::unexpected(map, line);
             }
         }
+        Ok(())
     }
     /// Parse begin and end time code from first to return a Cue.
     fn parse_cue(&mut self, first: &str, id: Option<String>) -> io::Result<Cue> {
-        let (size, begin) = parse_duration(first, self.lines.current())?;
-        let (_, end) = parse_duration(
-            first[size..]
-                .trim_start()
-                .trim_start_matches("-->")
-                .trim_start(),
-            self.lines.current(),
-        )?;
-
-        let mut lines = vec![];
+        let (size, begin) = parse_duration(first, self.lines.current(), self.lenient)?;
+        let rest = first[size..]
+            .trim_start()
+            .trim_start_matches("-->")
+            .trim_start();
+        let (size, end) = parse_duration(rest, self.lines.current(), self.lenient)?;
+        let settings = match rest[size..].trim() {
+            "" => None,
+            s => Some(s.to_string()),
+        };
+
+        let mut lines: Vec<String> = vec![];
         loop {
             match self.lines.next() {
                 Some(Err(e)) => return Err(e),
                 None => break,
-                Some(Ok(l)) if l.len() == 0 => break,
+                Some(Ok(l))
+                    if l.is_empty() && self.blank_line_policy == BlankLinePolicy::Terminate =>
+                {
+                    break
+                }
+                Some(Ok(l))
+                    if l.contains("-->") && self.blank_line_policy == BlankLinePolicy::Keep =>
+                {
+                    self.pending.push(l);
+                    let n = lines.len();
+                    if n >= 2 && !lines[n - 1].is_empty() && lines[n - 2].is_empty() {
+                        self.pending.extend(lines.pop());
+                    }
+                    break;
+                }
                 Some(Ok(l)) => lines.push(l),
             }
         }
+        while lines.last().is_some_and(String::is_empty) {
+            lines.pop();
+        }
 
         let id = match id {
-            Some(id) if id.chars().any(|c| !c.is_numeric()) => Some(id),
+            Some(id) if self.keep_ids || id.chars().any(|c| !c.is_numeric()) => Some(id),
             _ => None,
         };
 
-        Ok(Cue::new(id, begin, end, lines))
+        let map = |d: Duration| (d + self.mpegts).saturating_sub(self.local);
+        let mut c = Cue::new(id, map(begin), map(end), lines);
+        c.settings = settings;
+        Ok(c)
+    }
+}
+impl<'a> WebVTTParser<Box<dyn Read + 'a>> {
+    /// Create a parser of a boxed reader, so its type does not depend on the
+    /// reader type, like when the input is chosen at runtime.
+    pub fn new_boxed<R: Read + 'a>(r: R) -> io::Result<Self> {
+        Self::new(Box::new(r))
+    }
+}
+impl<R: Read> SubtitleParser for WebVTTParser<R> {
+    fn format(&self) -> Format {
+        Format::WebVTT
+    }
+    fn styles(&self) -> &[String] {
+        &self.styles
+    }
+    fn regions(&self) -> &[Region] {
+        &self.regions
     }
 }
 impl<R: Read> Iterator for WebVTTParser<R> {
@@ -138,7 +353,7 @@ impl<R: Read> Iterator for WebVTTParser<R> {
             }
             Err(e) => {
                 self.end = true;
-                Some(Err(e))
+                Some(Err(self.lines.locate(e)))
             }
         }
     }
@@ -176,121 +391,260 @@ identifier
     )
     .unwrap();
 
+    let mut c = cue(None, 1000, 4000, &["Never drink liquid nitrogen."]);
+    c.settings = Some(String::from("line:63% position:72% align:start"));
+    assert_eq!(p.next().unwrap().unwrap(), c);
+
     assert_eq!(
         p.next().unwrap().unwrap(),
-        Cue::new(
-            None,
-            Duration::new(1, 0),
-            Duration::new(4, 0),
-            vec![String::from("Never drink liquid nitrogen.")],
+        cue(
+            Some("identifier"),
+            5000,
+            9000,
+            &["— It will perforate your stomach.", "— You could die."]
         )
     );
+}
 
+#[test]
+fn parser_note_only() {
+    for input in [
+        "WEBVTT\n\nNOTE Hello World",
+        "WEBVTT\n\nNOTE Hello\n\nNOTE\nWorld\n",
+        "WEBVTT\n\nNOTE Hello\n\n \nNOTE World",
+        "WEBVTT\n\nNOTE Hello\n\t\n\nNOTE World\n\n",
+    ] {
+        let mut p = WebVTTParser::new(input.as_bytes()).unwrap();
+        assert!(p.next().is_none(), "{:?}", input);
+    }
+}
+
+#[test]
+fn parser_blank_line_policy() {
+    let input = "WEBVTT
+
+00:01.000 --> 00:04.000
+Never drink
+
+liquid nitrogen.
+
+id
+00:05.000 --> 00:09.000
+It will perforate your stomach.
+
+";
+    // The cue is truncated, then the rest of the text is an id without time.
+    let mut p = WebVTTParser::new(input.as_bytes()).unwrap();
     assert_eq!(
         p.next().unwrap().unwrap(),
-        Cue::new(
-            Some(String::from("identifier")),
-            Duration::new(5, 0),
-            Duration::new(9, 0),
-            vec![
-                String::from("— It will perforate your stomach."),
-                String::from("— You could die."),
-            ],
-        )
+        cue(None, 1000, 4000, &["Never drink"])
+    );
+    assert!(p.next().unwrap().is_err());
+
+    let p = WebVTTParser::new(input.as_bytes())
+        .unwrap()
+        .blank_line_policy(BlankLinePolicy::Keep);
+    assert_eq!(
+        p.map(|c| c.unwrap()).collect::<Vec<Cue>>(),
+        vec![
+            cue(None, 1000, 4000, &["Never drink", "", "liquid nitrogen."]),
+            cue(Some("id"), 5000, 9000, &["It will perforate your stomach."]),
+        ]
+    );
+}
+
+#[test]
+fn parser_header() {
+    let mut p = WebVTTParser::new(
+        "\u{FEFF}WEBVTT
+
+00:01.000 --> 00:04.000
+Hello"
+            .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(p.next().unwrap().unwrap().text, vec![String::from("Hello")]);
+
+    let err = WebVTTParser::new(
+        "1
+00:00:01,000 --> 00:00:04,000
+Hello"
+            .as_bytes(),
+    )
+    .err()
+    .unwrap();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    assert!(matches!(
+        err.get_ref().unwrap().downcast_ref::<ParseError>(),
+        Some(ParseError::MissingHeader)
+    ));
+}
+
+#[test]
+fn parser_timestamp_map() {
+    let mut p = WebVTTParser::new(
+        "WEBVTT
+X-TIMESTAMP-MAP=MPEGTS:900000,LOCAL:00:00:01.000
+
+00:01.000 --> 00:04.000
+Hello
+"
+        .as_bytes(),
+    )
+    .unwrap();
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        cue(None, 10000, 13000, &["Hello"])
+    );
+    assert!(p.next().is_none());
+
+    assert!(WebVTTParser::new("WEBVTT\nX-TIMESTAMP-MAP=MPEGTS:x\n".as_bytes()).is_err());
+}
+
+#[test]
+fn parser_lenient() {
+    let input = "WEBVTT\n\n00:01,000 --> 00:04,500\nHello\n";
+
+    let mut p = WebVTTParser::new(input.as_bytes()).unwrap();
+    let e = p.next().unwrap().unwrap_err();
+    assert!(matches!(
+        e.get_ref().unwrap().downcast_ref::<ParseError>(),
+        Some(ParseError::BadTimestamp { line: 3, raw: Some(raw), .. })
+            if raw == "00:01,000 --> 00:04,500"
+    ));
+
+    let mut p = WebVTTParser::new(input.as_bytes()).unwrap().lenient(true);
+    assert_eq!(
+        p.next().unwrap().unwrap(),
+        cue(None, 1000, 4500, &["Hello"])
     );
 }
 
 /// Parse the duration of the line line. Return the string readed length and the Duration.
-fn parse_duration(s: &str, line: usize) -> io::Result<(usize, Duration)> {
-    let len = match s.find('.') {
-        None => {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("Not found '.' for duration milliseconds (line {})", line),
-            ));
-        }
+/// If lenient, a comma is accepted as milliseconds separator.
+fn parse_duration(s: &str, line: usize, lenient: bool) -> io::Result<(usize, Duration)> {
+    let sep: &[char] = if lenient { &['.', ','] } else { &['.'] };
+    let len = match s.find(sep) {
+        None => return ParseError::timestamp("Not found '.' for duration milliseconds", s, line),
         Some(l) => l,
     };
 
-    let millis: u32 = match s.get(len + 1..len + 4).map(|s| s.parse::<u32>()) {
-        Some(Ok(n)) => n * 1_000_000,
+    let digits = s[len + 1..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .count();
+    let nanos: u32 = match parse_nanos(&s[len + 1..len + 1 + digits]) {
+        Some(nanos) => nanos,
         None => {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!(
-                    "Need 3 digit after the dot for milliseconds (Parse duration, line {})",
-                    line
-                ),
-            ))
-        }
-        Some(Err(err)) => {
-            return Err(io::Error::new(
-                ErrorKind::InvalidData,
-                format!("{} on {:?} (Parse duration, line {})", err, s, line),
-            ))
+            return ParseError::timestamp(
+                "Need digits after the dot for the fraction of second",
+                s,
+                line,
+            )
         }
     };
 
     let hhmmss = s[..len].split(':');
     match hhmmss.clone().count() {
         2 | 3 => {}
-        _ => return Err(io::Error::new(
-            ErrorKind::InvalidData,
-            format!(
-                "Wrong duration format (expected hh:mm:ss.ttt or mm:ss.ttt) on {:?} (Parse duration, line {})",
-                s, line
-            ),
-        )),
+        _ => {
+            return ParseError::timestamp(
+                "Wrong duration format (expected hh:mm:ss.ttt or mm:ss.ttt)",
+                s,
+                line,
+            )
+        }
     }
     let mut secs: u64 = 0;
     for ss in hhmmss {
         secs = secs * 60
-            + ss.parse::<u64>().map_err(|err| {
-                io::Error::new(
-                    ErrorKind::InvalidData,
-                    format!("{} on {:?} (Parse duration, line {})", err, s, line),
-                )
-            })?;
+            + match ss.parse::<u64>() {
+                Ok(n) => n,
+                Err(err) => return ParseError::timestamp(err, s, line),
+            };
     }
 
-    Ok((len + 4, Duration::new(secs, millis)))
+    Ok((len + 1 + digits, Duration::new(secs, nanos)))
 }
 #[test]
 fn test_parse_duration() {
     assert_eq!(
         (9, Duration::new(13 * 60 + 16, 500_000_000)),
-        parse_duration("13:16.500", 0).unwrap()
+        parse_duration("13:16.500", 0, false).unwrap()
     );
     assert_eq!(
         (14, Duration::new(7892 * 3600 + 13 * 60 + 16, 500_000_000)),
-        parse_duration("7892:13:16.500", 0).unwrap()
+        parse_duration("7892:13:16.500", 0, false).unwrap()
+    );
+    assert_eq!(
+        (7, Duration::new(13 * 60 + 16, 500_000_000)),
+        parse_duration("13:16.5", 0, false).unwrap()
+    );
+    assert_eq!(
+        (8, Duration::new(13 * 60 + 16, 540_000_000)),
+        parse_duration("13:16.54 -->", 0, false).unwrap()
+    );
+    assert_eq!(
+        (12, Duration::new(13 * 60 + 16, 123_456_000)),
+        parse_duration("13:16.123456", 0, false).unwrap()
+    );
+    assert!(parse_duration("13:16.", 0, false).is_err());
+
+    assert!(parse_duration("13:16,500", 0, false).is_err());
+    assert_eq!(
+        (9, Duration::new(13 * 60 + 16, 500_000_000)),
+        parse_duration("13:16,500", 0, true).unwrap()
     );
 }
 
 /// Write all Cues from the input Iterator into the write W. Use WebVTT subtitle format.
 /// Return the number fo writed cue.
-pub fn out<I, W>(cues: I, mut w: W) -> Result<usize, std::io::Error>
+pub fn out<I, W>(cues: I, w: W) -> Result<usize, std::io::Error>
+where
+    W: Write,
+    I: std::iter::Iterator<Item = Cue>,
+{
+    out_with(cues, w, &WriteOptions::default())
+}
+
+/// Like `out` with the writing options.
+pub fn out_with<I, W>(cues: I, w: W, options: &WriteOptions) -> Result<usize, std::io::Error>
 where
     W: Write,
     I: std::iter::Iterator<Item = Cue>,
 {
-    w.write(b"WEBVTT\n\n")?;
+    let mut w = options.line_ending.writer(w);
+    w.write_all(b"WEBVTT\n")?;
+    for region in options.regions.iter() {
+        write!(w, "\nREGION\n{}\n", region)?;
+    }
+    for style in options.styles.iter() {
+        write!(w, "\nSTYLE\n{}\n", style)?;
+    }
 
     let mut nb = 0;
     for c in cues {
-        if let Some(id) = c.id {
-            write!(w, "{}\n", id)?;
+        w.write_all(b"\n")?;
+        match c.id.filter(|_| options.ids != IdPolicy::Drop) {
+            Some(id) => writeln!(w, "{}", id)?,
+            None if options.number_cues => writeln!(w, "{}", nb + 1)?,
+            None => {}
         }
         write_duration(&mut w, &c.begin)?;
-        w.write(b" --> ")?;
+        w.write_all(b" --> ")?;
         write_duration(&mut w, &c.end)?;
-        w.write(b"\n")?;
+        if let Some(settings) = c.settings {
+            write!(w, " {}", settings)?;
+        }
+        w.write_all(b"\n")?;
         for l in c.text {
-            write!(w, "{}\n", l)?;
+            writeln!(w, "{}", options.tags.apply(&l, Format::WebVTT))?;
         }
-        w.write(b"\n")?;
         nb += 1;
     }
+    if !options.no_trailing_newline {
+        w.write_all(b"\n")?;
+    }
 
     Ok(nb)
 }
@@ -305,7 +659,7 @@ fn test_out() {
     assert_eq!(
         out(
             vec![
-                Cue::new(None, dur(0), dur(05), vec![String::from("Hello World")]),
+                Cue::new(None, dur(0), dur(5), vec![String::from("Hello World")]),
                 Cue::new(
                     Some("Yolo".to_string()),
                     dur(5),
@@ -337,29 +691,238 @@ On va commencer.
 
 "
     );
+
+    let options = WriteOptions {
+        no_trailing_newline: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    let cues = vec![
+        Cue::new(None, dur(0), dur(5), vec![String::from("Hello")]),
+        Cue::new(None, dur(5), dur(10), vec![String::from("World")]),
+    ];
+    out_with(cues.into_iter(), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "WEBVTT\n\n00:00.000 --> 00:05.000\nHello\n\n00:05.000 --> 00:10.000\nWorld\n"
+    );
 }
+#[test]
+fn test_out_styles() {
+    let vtt = "WEBVTT
 
-fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), std::io::Error> {
-    let sec = d.as_secs();
-    let min = sec / 3600;
-    if min == 0 {
-        write!(
-            w,
-            "{:02}:{:02}.{:03}",
-            sec / 60 % 60,
-            sec % 60,
-            d.subsec_millis()
-        )
-    } else {
-        write!(
-            w,
-            "{:02}:{:02}:{:02}.{:03}",
-            min,
-            sec / 60 % 60,
-            sec % 60,
-            d.subsec_millis()
-        )
+STYLE
+::cue(b) { color: red; }
+
+00:01.000 --> 00:02.000
+<b>Hello</b>
+
+";
+    let p = WebVTTParser::new(vtt.as_bytes()).unwrap();
+    assert_eq!(p.styles(), &[String::from("::cue(b) { color: red; }")]);
+    assert_eq!(p.count(), 1);
+
+    let mut output: Vec<u8> = Vec::new();
+    super::convert(
+        vtt.as_bytes(),
+        Format::WebVTT,
+        &mut output,
+        Format::WebVTT,
+        super::Delta::None,
+    )
+    .unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), vtt);
+}
+#[test]
+fn test_out_regions() {
+    let vtt = "WEBVTT
+
+REGION
+id:fred
+width:40%
+lines:3
+regionanchor:0%,100%
+viewportanchor:10%,90%
+scroll:up
+
+00:01.000 --> 00:02.000 region:fred align:left
+Hello
+
+";
+    let p = WebVTTParser::new("WEBVTT\n\nREGION\nid:fred width:40% lines:3\n".as_bytes()).unwrap();
+    assert_eq!(
+        p.regions(),
+        &[Region {
+            id: String::from("fred"),
+            width: Some(String::from("40%")),
+            lines: Some(3),
+            ..Region::default()
+        }]
+    );
+
+    let mut output: Vec<u8> = Vec::new();
+    super::convert(
+        vtt.as_bytes(),
+        Format::WebVTT,
+        &mut output,
+        Format::WebVTT,
+        super::Delta::None,
+    )
+    .unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), vtt);
+}
+#[test]
+fn test_out_number_cues() {
+    let options = WriteOptions {
+        number_cues: true,
+        ..WriteOptions::default()
+    };
+    let mut output: Vec<u8> = Vec::new();
+    let cues = vec![
+        cue(None, 1000, 2000, &["Hello"]),
+        cue(Some("intro"), 2000, 3000, &["Hello"]),
+        cue(None, 3000, 4000, &["Hello"]),
+    ];
+    out_with(cues.into_iter(), &mut output, &options).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "WEBVTT
+
+1
+00:01.000 --> 00:02.000
+Hello
+
+intro
+00:02.000 --> 00:03.000
+Hello
+
+3
+00:03.000 --> 00:04.000
+Hello
+
+"
+    );
+}
+#[test]
+fn test_out_ids() {
+    let vtt = "WEBVTT
+
+intro
+00:01.000 --> 00:02.000
+Hello
+
+2
+00:03.000 --> 00:04.000
+World
+
+";
+    let round_trip = |keep: bool, ids: IdPolicy| {
+        let cues = WebVTTParser::new(vtt.as_bytes()).unwrap().keep_ids(keep);
+        let cues = cues.map(Result::unwrap);
+        let mut output: Vec<u8> = Vec::new();
+        let options = WriteOptions {
+            ids,
+            ..WriteOptions::default()
+        };
+        out_with(cues, &mut output, &options).unwrap();
+        String::from_utf8(output).unwrap()
+    };
+
+    assert_eq!(round_trip(true, IdPolicy::Keep), vtt);
+    assert_eq!(
+        round_trip(false, IdPolicy::Auto),
+        vtt.replace("\n2\n", "\n")
+    );
+    assert_eq!(
+        round_trip(true, IdPolicy::Drop),
+        vtt.replace("\nintro\n", "\n").replace("\n2\n", "\n")
+    );
+}
+#[test]
+fn test_out_settings() {
+    let mut c = cue(None, 1000, 4000, &["Hello"]);
+    c.settings = Some(String::from("line:63% align:start"));
+
+    let mut output: Vec<u8> = Vec::new();
+    out(vec![c.clone()].into_iter(), &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "WEBVTT
+
+00:01.000 --> 00:04.000 line:63% align:start
+Hello
+
+"
+    );
+
+    let mut output: Vec<u8> = Vec::new();
+    super::srt_out(vec![c].into_iter(), &mut output).unwrap();
+    assert_eq!(
+        std::str::from_utf8(&output).unwrap(),
+        "1
+00:00:01,000 --> 00:00:04,000
+Hello
+
+"
+    );
+}
+#[cfg(test)]
+use super::TagStyle;
+#[test]
+fn test_out_tags() {
+    fn convert(s: &str, from: Format, to: Format, tags: TagStyle) -> String {
+        let options = WriteOptions {
+            tags,
+            ..WriteOptions::default()
+        };
+        let mut output: Vec<u8> = Vec::new();
+        let cues = super::parse_str(s, from).map(|c| c.unwrap());
+        match to {
+            Format::WebVTT => out_with(cues, &mut output, &options),
+            _ => super::srt::out_with(cues, &mut output, &options),
+        }
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    let srt = "1\n00:00:01,000 --> 00:00:02,000\n<i>text</i>\n\n";
+    let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000\n<i>text</i>\n\n";
+    for tags in [TagStyle::Verbatim, TagStyle::Convert] {
+        assert_eq!(convert(srt, Format::Srt, Format::WebVTT, tags), vtt);
+        assert_eq!(convert(vtt, Format::WebVTT, Format::Srt, tags), srt);
     }
+
+    assert_eq!(
+        convert(
+            "1\n00:00:01,000 --> 00:00:02,000\n<font color=\"red\">text</font>\n",
+            Format::Srt,
+            Format::WebVTT,
+            TagStyle::Convert
+        ),
+        "WEBVTT\n\n00:01.000 --> 00:02.000\n<c.red>text</c>\n\n"
+    );
+    assert_eq!(
+        convert(vtt, Format::WebVTT, Format::Srt, TagStyle::Strip),
+        "1\n00:00:01,000 --> 00:00:02,000\ntext\n\n"
+    );
+}
+#[test]
+fn test_out_voice() {
+    let vtt = "WEBVTT\n\n00:01.000 --> 00:02.000\n<v Bob>Hello <00:01.500>World\n\n";
+    let cues: Vec<Cue> = super::parse_str(vtt, Format::WebVTT)
+        .map(|c| c.unwrap())
+        .collect();
+    let lines = cues[0].lines();
+    assert_eq!(lines[0].speaker.as_deref(), Some("Bob"));
+    assert_eq!(lines[0].text, "Hello <00:01.500>World");
+
+    let mut output: Vec<u8> = Vec::new();
+    out(cues.into_iter(), &mut output).unwrap();
+    assert_eq!(std::str::from_utf8(&output).unwrap(), vtt);
+}
+
+fn write_duration<W: Write>(w: &mut W, d: &Duration) -> Result<(), std::io::Error> {
+    write!(w, "{}", WebVTTTimestamp(*d))
 }
 #[test]
 fn test_write_duration() {
@@ -372,4 +935,39 @@ fn test_write_duration() {
     let d = Duration::new(2 * 3600 + 3 * 60 + 5, 84 * 1_000_000);
     write_duration(&mut out, &d).unwrap();
     assert_eq!(std::str::from_utf8(&out).unwrap(), "02:03:05.084");
+
+    let mut out: Vec<u8> = Vec::new();
+    let d = Duration::new(100 * 3600 + 3 * 60 + 5, 84 * 1_000_000);
+    write_duration(&mut out, &d).unwrap();
+    assert_eq!(std::str::from_utf8(&out).unwrap(), "100:03:05.084");
+    assert_eq!(parse_duration("100:03:05.084", 1, false).unwrap(), (13, d));
+}
+
+/// Display a duration as a WebVTT time code (`mm:ss.ttt` or `hh:mm:ss.ttt`).
+/// The hours have two digits or more, past 99 hours.
+pub struct WebVTTTimestamp(pub Duration);
+impl fmt::Display for WebVTTTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let d = &self.0;
+        let sec = d.as_secs();
+        let hours = sec / 3600;
+        if hours == 0 {
+            write!(
+                f,
+                "{:02}:{:02}.{:03}",
+                sec / 60 % 60,
+                sec % 60,
+                d.subsec_millis()
+            )
+        } else {
+            write!(
+                f,
+                "{:02}:{:02}:{:02}.{:03}",
+                hours,
+                sec / 60 % 60,
+                sec % 60,
+                d.subsec_millis()
+            )
+        }
+    }
 }